@@ -3,15 +3,28 @@ pub mod sounds;
 
 use registers::PulseRegister;
 use registers::NoiseRegister;
+use self::registers::DmcRegister;
 use self::registers::TriangleRegister;
 use self::sounds::Transmitters;
+use crate::interrupt::IrqSource;
+use crate::savestate::{Snapshot, StateReader, StateWriter};
+
+// CPU cycles per frame counter sequence (NTSC)
+const FOUR_STEP_SEQUENCE_CYCLES: usize = 29830;
+const FIVE_STEP_SEQUENCE_CYCLES: usize = 37282;
 
 pub struct NesAPU {
     square1: PulseRegister,
     square2: PulseRegister,
     triangle: TriangleRegister,
     noise: NoiseRegister,
+    dmc: DmcRegister,
     txs: Transmitters,
+
+    is_five_step_mode: bool,
+    is_frame_irq_inhibit: bool,
+    frame_irq_flag: bool,
+    frame_cycles: usize,
 }
 
 impl NesAPU {
@@ -21,8 +34,35 @@ impl NesAPU {
             square2: PulseRegister::new(),
             triangle: TriangleRegister::new(),
             noise: NoiseRegister::new(),
-            txs
+            dmc: DmcRegister::new(),
+            txs,
+
+            is_five_step_mode: false,
+            is_frame_irq_inhibit: false,
+            frame_irq_flag: false,
+            frame_cycles: 0,
+        }
+    }
+
+    // Every register starts out cleared, with the frame counter in four-step mode.
+    pub fn power_on(&mut self) {
+        self.reset();
+        self.write_frame_counter(0);
+    }
+
+    // Reset silences the channels like writing 0 to $4015. Nothing here models
+    // length counters, so that means clearing the channel registers.
+    pub fn reset(&mut self) {
+        for offset in 0..4 {
+            self.write_square1(0x4000 + offset, 0);
+            self.write_square2(0x4004 + offset, 0);
+            self.write_triangle(0x4008 + offset, 0);
+            self.write_noise(0x400C + offset, 0);
+            self.write_dmc(0x4010 + offset, 0);
         }
+        self.write_status(0);
+        self.frame_irq_flag = false;
+        self.frame_cycles = 0;
     }
 
     pub fn write_square1(&mut self, addr: u16, data: u8) {
@@ -44,4 +84,99 @@ impl NesAPU {
         self.noise.write(addr, data);
         self.txs.noise.send(self.noise.get_note()).unwrap();
     }
-}
\ No newline at end of file
+
+    pub fn write_dmc(&mut self, addr: u16, data: u8) {
+        self.dmc.write(addr, data);
+    }
+
+    pub fn write_status(&mut self, data: u8) {
+        self.dmc.set_enabled(data & 0b0001_0000 != 0);
+        self.dmc.irq_flag = false;
+    }
+
+    // IF-D ----
+    // ||| 
+    // ||+------- DMC active
+    // |+-------- Frame interrupt
+    // +--------- DMC interrupt
+    pub fn read_status(&mut self) -> u8 {
+        let mut status = 0;
+        if self.dmc.is_active() {
+            status |= 0b0001_0000;
+        }
+        if self.frame_irq_flag {
+            status |= 0b0100_0000;
+        }
+        if self.dmc.irq_flag {
+            status |= 0b1000_0000;
+        }
+        self.frame_irq_flag = false;
+        status
+    }
+
+    pub fn write_frame_counter(&mut self, data: u8) {
+        self.is_five_step_mode = data & 0b1000_0000 != 0;
+        self.is_frame_irq_inhibit = data & 0b0100_0000 != 0;
+        if self.is_frame_irq_inhibit {
+            self.frame_irq_flag = false;
+        }
+        self.frame_cycles = 0;
+    }
+
+    pub fn tick(&mut self, cycles: u8) {
+        self.dmc.tick(cycles);
+
+        self.frame_cycles += cycles as usize;
+        let sequence_cycles = if self.is_five_step_mode {
+            FIVE_STEP_SEQUENCE_CYCLES
+        } else {
+            FOUR_STEP_SEQUENCE_CYCLES
+        };
+        if self.frame_cycles >= sequence_cycles {
+            self.frame_cycles -= sequence_cycles;
+            if !self.is_five_step_mode && !self.is_frame_irq_inhibit {
+                self.frame_irq_flag = true;
+            }
+        }
+    }
+
+    pub fn irq_sources(&self) -> IrqSource {
+        let mut sources = IrqSource::empty();
+        sources.set(IrqSource::FRAME_COUNTER, self.frame_irq_flag);
+        sources.set(IrqSource::DMC, self.dmc.irq_flag);
+        sources
+    }
+}
+
+impl Snapshot for NesAPU {
+    fn save_state(&self, w: &mut StateWriter) {
+        self.square1.save_state(w);
+        self.square2.save_state(w);
+        self.triangle.save_state(w);
+        self.noise.save_state(w);
+        self.dmc.save_state(w);
+        w.write_bool(self.is_five_step_mode);
+        w.write_bool(self.is_frame_irq_inhibit);
+        w.write_bool(self.frame_irq_flag);
+        w.write_usize(self.frame_cycles);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), String> {
+        self.square1.load_state(r)?;
+        self.square2.load_state(r)?;
+        self.triangle.load_state(r)?;
+        self.noise.load_state(r)?;
+        self.dmc.load_state(r)?;
+        self.is_five_step_mode = r.read_bool()?;
+        self.is_frame_irq_inhibit = r.read_bool()?;
+        self.frame_irq_flag = r.read_bool()?;
+        self.frame_cycles = r.read_usize()?;
+
+        // bring the audio thread in line with the restored registers
+        self.txs.square1.send(self.square1.get_note()).unwrap();
+        self.txs.square2.send(self.square2.get_note()).unwrap();
+        self.txs.triangle.send(self.triangle.get_note()).unwrap();
+        self.txs.noise.send(self.noise.get_note()).unwrap();
+        Ok(())
+    }
+}
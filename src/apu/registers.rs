@@ -1,7 +1,9 @@
 mod pulse;
 mod triangle;
 mod noise;
+mod dmc;
 
 pub use pulse::PulseRegister;
 pub use triangle::TriangleRegister;
-pub use noise::NoiseRegister;
\ No newline at end of file
+pub use noise::NoiseRegister;
+pub use dmc::DmcRegister;
\ No newline at end of file
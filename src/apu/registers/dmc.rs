@@ -0,0 +1,111 @@
+use crate::savestate::{Snapshot, StateReader, StateWriter};
+
+// Only the sample byte counter is modelled, which is what drives the DMC IRQ.
+// Sample data isn't fetched from memory and nothing is played back.
+const RATE_TABLE: [u16; 16] = [
+    428, 380, 340, 320, 286, 254, 226, 214, 190, 160, 142, 128, 106, 84, 72, 54,
+];
+
+pub struct DmcRegister {
+    is_irq_enabled: bool,
+    is_loop: bool,
+    rate: u8,
+    sample_length: u8,
+
+    bytes_remaining: u16,
+    cycles: u16,
+    pub irq_flag: bool,
+}
+
+impl DmcRegister {
+    pub fn new() -> Self {
+        DmcRegister {
+            is_irq_enabled: false,
+            is_loop: false,
+            rate: 0,
+            sample_length: 0,
+
+            bytes_remaining: 0,
+            cycles: 0,
+            irq_flag: false,
+        }
+    }
+
+    pub fn write(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x4010 => {
+                self.is_irq_enabled = data & 0b1000_0000 != 0;
+                self.is_loop = data & 0b0100_0000 != 0;
+                self.rate = data & 0b0000_1111;
+                if !self.is_irq_enabled {
+                    self.irq_flag = false;
+                }
+            }
+            0x4011 | 0x4012 => {}
+            0x4013 => {
+                self.sample_length = data;
+            }
+            _ => panic!("not possible")
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.bytes_remaining > 0
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        if !enabled {
+            self.bytes_remaining = 0;
+        } else if self.bytes_remaining == 0 {
+            self.restart();
+        }
+    }
+
+    fn restart(&mut self) {
+        self.bytes_remaining = self.sample_length as u16 * 16 + 1;
+        self.cycles = 0;
+    }
+
+    pub fn tick(&mut self, cycles: u8) {
+        if self.bytes_remaining == 0 {
+            return;
+        }
+        // one sample byte is consumed every 8 output bits
+        let byte_period = RATE_TABLE[self.rate as usize] * 8;
+        self.cycles += cycles as u16;
+        if self.cycles >= byte_period {
+            self.cycles -= byte_period;
+            self.bytes_remaining -= 1;
+            if self.bytes_remaining == 0 {
+                if self.is_loop {
+                    self.restart();
+                } else if self.is_irq_enabled {
+                    self.irq_flag = true;
+                }
+            }
+        }
+    }
+}
+
+impl Snapshot for DmcRegister {
+    fn save_state(&self, w: &mut StateWriter) {
+        w.write_bool(self.is_irq_enabled);
+        w.write_bool(self.is_loop);
+        w.write_u8(self.rate);
+        w.write_u8(self.sample_length);
+        w.write_u16(self.bytes_remaining);
+        w.write_u16(self.cycles);
+        w.write_bool(self.irq_flag);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), String> {
+        self.is_irq_enabled = r.read_bool()?;
+        self.is_loop = r.read_bool()?;
+        self.rate = r.read_u8()? & 0b1111;
+        self.sample_length = r.read_u8()?;
+        self.bytes_remaining = r.read_u16()?;
+        self.cycles = r.read_u16()?;
+        self.irq_flag = r.read_bool()?;
+        Ok(())
+    }
+}
@@ -1,4 +1,5 @@
 use crate::{apu::sounds::NoiseNote, cpu::CPU_FREQ};
+use crate::savestate::{Snapshot, StateReader, StateWriter};
 
 pub struct NoiseRegister {
     is_length_counter_halt: bool,
@@ -75,3 +76,24 @@ impl NoiseRegister {
         }
     }
 }
+
+impl Snapshot for NoiseRegister {
+    fn save_state(&self, w: &mut StateWriter) {
+        w.write_bool(self.is_length_counter_halt);
+        w.write_bool(self.is_constant_volume);
+        w.write_u8(self.volume);
+        w.write_bool(self.is_long_period);
+        w.write_u8(self.period);
+        w.write_u8(self.length_counter_load);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), String> {
+        self.is_length_counter_halt = r.read_bool()?;
+        self.is_constant_volume = r.read_bool()?;
+        self.volume = r.read_u8()?;
+        self.is_long_period = r.read_bool()?;
+        self.period = r.read_u8()? & 0b1111;
+        self.length_counter_load = r.read_u8()?;
+        Ok(())
+    }
+}
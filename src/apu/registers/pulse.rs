@@ -1,5 +1,6 @@
 use crate::apu::sounds::PulseNote;
 use crate::cpu::CPU_FREQ;
+use crate::savestate::{Snapshot, StateReader, StateWriter};
 
 pub struct PulseRegister {
     duty: u8,
@@ -86,3 +87,32 @@ impl PulseRegister {
         }
     }
 }
+
+impl Snapshot for PulseRegister {
+    fn save_state(&self, w: &mut StateWriter) {
+        w.write_u8(self.duty);
+        w.write_bool(self.is_length_counter_halt);
+        w.write_bool(self.is_constant_volume);
+        w.write_u8(self.volume);
+        w.write_bool(self.is_sweep_enable);
+        w.write_u8(self.sweep_timer_count);
+        w.write_bool(self.is_sweep_negate);
+        w.write_u8(self.sweep_shift_count);
+        w.write_u16(self.timer);
+        w.write_u8(self.length_counter_load);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), String> {
+        self.duty = r.read_u8()? & 0b11;
+        self.is_length_counter_halt = r.read_bool()?;
+        self.is_constant_volume = r.read_bool()?;
+        self.volume = r.read_u8()?;
+        self.is_sweep_enable = r.read_bool()?;
+        self.sweep_timer_count = r.read_u8()?;
+        self.is_sweep_negate = r.read_bool()?;
+        self.sweep_shift_count = r.read_u8()?;
+        self.timer = r.read_u16()?;
+        self.length_counter_load = r.read_u8()?;
+        Ok(())
+    }
+}
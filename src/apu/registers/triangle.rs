@@ -1,5 +1,6 @@
 use crate::apu::sounds::TriangleNote;
 use crate::cpu::CPU_FREQ;
+use crate::savestate::{Snapshot, StateReader, StateWriter};
 
 pub struct TriangleRegister {
     is_length_counter_halt: bool,
@@ -48,3 +49,20 @@ impl TriangleRegister {
         }
     }
 }
+
+impl Snapshot for TriangleRegister {
+    fn save_state(&self, w: &mut StateWriter) {
+        w.write_bool(self.is_length_counter_halt);
+        w.write_u8(self.counter_reload_value);
+        w.write_u16(self.timer);
+        w.write_u8(self.length_counter_load);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), String> {
+        self.is_length_counter_halt = r.read_bool()?;
+        self.counter_reload_value = r.read_u8()?;
+        self.timer = r.read_u16()?;
+        self.length_counter_load = r.read_u8()?;
+        Ok(())
+    }
+}
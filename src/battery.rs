@@ -0,0 +1,91 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::mapper::SharedMapper;
+
+// flush roughly every 5 seconds of emulated time
+const FLUSH_INTERVAL_FRAMES: u32 = 60 * 5;
+
+// Battery-backed PRG RAM kept in a .sav file next to the ROM.
+pub struct BatterySave {
+    path: PathBuf,
+    saved: Vec<u8>,
+    frames: u32,
+}
+
+impl BatterySave {
+    pub fn for_rom(rom_path: &Path) -> Self {
+        let path = rom_path.with_extension("sav");
+        let saved = fs::read(&path).unwrap_or_default();
+        BatterySave {
+            path,
+            saved,
+            frames: 0,
+        }
+    }
+
+    // Contents of the .sav file as it was last read or written.
+    pub fn saved(&self) -> &[u8] {
+        &self.saved
+    }
+
+    pub fn on_frame(&mut self, mapper: &SharedMapper) {
+        self.frames += 1;
+        if self.frames >= FLUSH_INTERVAL_FRAMES {
+            self.frames = 0;
+            self.flush(mapper);
+        }
+    }
+
+    pub fn flush(&mut self, mapper: &SharedMapper) {
+        let ram = mapper.borrow().prg_ram().to_vec();
+        if ram == self.saved {
+            return;
+        }
+        match fs::write(&self.path, &ram) {
+            Ok(()) => self.saved = ram,
+            Err(err) => eprintln!("Failed to write {}: {}", self.path.display(), err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartridge::{test::ines_image, Rom};
+    use crate::mapper;
+
+    #[test]
+    fn test_prg_ram_survives_a_restart() {
+        let dir = std::env::temp_dir().join(format!("nes-rs-battery-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let rom_path = dir.join("game.nes");
+        let sav_path = dir.join("game.sav");
+        let mut raw = ines_image(0, 1, 1);
+        raw[6] |= 0b0000_0010;
+        assert!(Rom::new(&raw).unwrap().has_battery);
+
+        let mapper = mapper::new(Rom::new(&raw).unwrap());
+        mapper.borrow_mut().cpu_write(0x6000, 0x42);
+        mapper.borrow_mut().cpu_write(0x7FFF, 0x24);
+        let mut battery = BatterySave::for_rom(&rom_path);
+        assert!(battery.saved().is_empty());
+        for _ in 1..FLUSH_INTERVAL_FRAMES {
+            battery.on_frame(&mapper);
+        }
+        assert!(!sav_path.exists());
+        battery.on_frame(&mapper);
+        assert_eq!(fs::read(&sav_path).unwrap().len(), 0x2000);
+
+        // the next run starts from the .sav file
+        let battery = BatterySave::for_rom(&rom_path);
+        let mapper = mapper::new(Rom::new(&raw).unwrap());
+        mapper.borrow_mut().load_prg_ram(battery.saved());
+        assert_eq!(mapper.borrow_mut().cpu_read(0x6000), Some(0x42));
+        assert_eq!(mapper.borrow_mut().cpu_read(0x7FFF), Some(0x24));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,55 @@
+use std::{env, fs, process, time::Instant};
+
+use getopts::Options;
+use nes_rs::cartridge::Rom;
+use nes_rs::emulator::Emulator;
+
+const SAMPLE_RATE: f32 = 44100.0;
+const DEFAULT_FRAMES: usize = 1200;
+const BUNDLED_ROMS: [&str; 2] = ["mario.nes", "Alter_Ego.nes"];
+
+// Measures how many frames per second the core emulates with nothing attached.
+// Build with --release, debug builds are an order of magnitude slower.
+fn main() {
+    let mut opts = Options::new();
+    opts.optopt("f", "frames", "Number of frames to run per ROM (default 1200).", "N");
+    opts.optflag("h", "help", "Print this help.");
+
+    let args: Vec<String> = env::args().collect();
+    let match_opts = match opts.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(err) => fail(&err.to_string()),
+    };
+    if match_opts.opt_present("h") {
+        print!("{}", opts.usage("Usage: nes-bench [options] [ROM...]\n\nRuns the bundled ROMs when none are given."));
+        return;
+    }
+
+    let frames: usize = match_opts
+        .opt_get_default("f", DEFAULT_FRAMES)
+        .unwrap_or_else(|err| fail(&format!("Invalid frame count: {}", err)));
+    let roms: Vec<String> = if match_opts.free.is_empty() {
+        BUNDLED_ROMS.iter().map(|rom| rom.to_string()).collect()
+    } else {
+        match_opts.free.clone()
+    };
+
+    for path in &roms {
+        let bytes = fs::read(path).unwrap_or_else(|err| fail(&format!("{}: {}", path, err)));
+        let rom = Rom::new(&bytes).unwrap_or_else(|err| fail(&format!("{}: {}", path, err)));
+        let mut emulator = Emulator::new(rom, SAMPLE_RATE);
+
+        let start = Instant::now();
+        for _ in 0..frames {
+            emulator.run_frame();
+            emulator.audio_samples();
+        }
+        let elapsed = start.elapsed().as_secs_f64();
+        println!("{:<16} {} frames in {:.2}s, {:.1} fps", path, frames, elapsed, frames as f64 / elapsed);
+    }
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(1)
+}
@@ -1,9 +1,17 @@
-use crate::cpu::Mem;
+mod flat;
+mod ram_init;
+
+use crate::cpu::{CpuBus, Mem};
 use crate::cartridge::Rom;
 use crate::ppu::NesPPU;
 use crate::apu::NesAPU;
 use crate::ppu::PPU;
 use crate::joypad::Joypad;
+use crate::interrupt::IrqSource;
+use crate::mapper::{self, SharedMapper};
+use crate::savestate::{Snapshot, StateReader, StateWriter};
+pub use flat::FlatBus;
+pub use ram_init::RamInit;
 
 const RAM: u16 = 0x0000;
 const RAM_MIRRORS_END: u16 = 0x1FFF;
@@ -11,11 +19,15 @@ const PPU_REGISTERS_MIRRORS_END: u16 = 0x3FFF;
 
 pub struct Bus<'call> {
     cpu_vram: [u8; 2048],
-    prg_rom: Vec<u8>,
+    ram_init: RamInit,
+    // last value driven on the CPU data bus, what unmapped reads return
+    open_bus: u8,
+    mapper: SharedMapper,
     ppu: NesPPU,
     apu: NesAPU,
 
     cycles: usize,
+    frames: usize,
     gameloop_callback: Box<dyn FnMut(&NesPPU, &mut Joypad) + 'call>,
     joypad1: Joypad,
 }
@@ -25,32 +37,77 @@ impl<'a> Bus<'a> {
     where
         F: FnMut(&NesPPU, &mut Joypad) + 'call,
     {
-        let ppu = NesPPU::new(rom.chr_rom, rom.is_chr_ram, rom.screen_mirroring);
+        let mapper = mapper::new(rom);
+        let ppu = NesPPU::new(mapper.clone());
         Bus {
             cpu_vram: [0; 2048],
-            prg_rom: rom.prg_rom,
+            ram_init: RamInit::default(),
+            open_bus: 0,
+            mapper,
             ppu,
             apu,
             cycles: 0,
+            frames: 0,
             gameloop_callback: Box::from(gameloop_callback),
             joypad1: Joypad::new(),
         }
     }
 
-    fn read_prg_rom(&self, mut addr: u16) -> u8 {
-        addr -= 0x8000;
-        if self.prg_rom.len() == 0x4000 && addr >= 0x4000 {
-            addr %= 0x4000;
-        }
-        self.prg_rom[addr as usize]
+    // Takes effect at the next power on.
+    pub fn set_ram_init(&mut self, ram_init: RamInit) {
+        self.ram_init = ram_init;
+    }
+
+    pub fn mapper(&self) -> SharedMapper {
+        self.mapper.clone()
+    }
+
+    pub fn ppu(&self) -> &NesPPU {
+        &self.ppu
+    }
+
+    pub fn joypad1(&mut self) -> &mut Joypad {
+        &mut self.joypad1
     }
 
-    pub fn tick(&mut self, cycles: u8) {
+    pub fn cycles(&self) -> usize {
+        self.cycles
+    }
+
+    // The tracer peeks at memory through mem_read and puts the latch back afterwards.
+    pub(crate) fn open_bus(&self) -> u8 {
+        self.open_bus
+    }
+
+    pub(crate) fn set_open_bus(&mut self, data: u8) {
+        self.open_bus = data;
+    }
+
+    // Number of frames the PPU has finished, counted at the start of vblank
+    // whether or not NMI is enabled.
+    pub fn frame_count(&self) -> usize {
+        self.frames
+    }
+
+    pub fn irq_sources(&self) -> IrqSource {
+        let mut sources = self.apu.irq_sources();
+        sources.set(IrqSource::MAPPER, self.mapper.borrow().irq_pending());
+        sources
+    }
+}
+
+impl CpuBus for Bus<'_> {
+    fn tick(&mut self, cycles: u8) {
         self.cycles += cycles as usize;
+        self.apu.tick(cycles);
 
         let nmi_before = self.ppu.nmi_interrupt.is_some();
         for _ in 0..(cycles * 3) {
+            let vblank_before = self.ppu.status.is_in_vblank();
             self.ppu.tick();
+            if !vblank_before && self.ppu.status.is_in_vblank() {
+                self.frames += 1;
+            }
         }
         let nmi_after = self.ppu.nmi_interrupt.is_some();
 
@@ -59,21 +116,64 @@ impl<'a> Bus<'a> {
         }
     }
 
-    pub fn poll_nmi_status(&mut self) -> Option<u8> {
+    fn poll_nmi_status(&mut self) -> Option<u8> {
         self.ppu.poll_nmi_interrupt()
     }
+
+    // Only the cartridge RAM keeps its contents across a power cycle.
+    fn power_on(&mut self) {
+        self.ram_init.fill(&mut self.cpu_vram);
+        self.open_bus = 0;
+        self.cycles = 0;
+        self.frames = 0;
+        self.mapper.borrow_mut().power_on();
+        self.ppu = NesPPU::new(self.mapper.clone());
+        self.apu.power_on();
+        self.joypad1.power_on();
+    }
+
+    // RAM and the cartridge keep their contents across a reset.
+    fn reset(&mut self) {
+        self.ppu.reset();
+        self.apu.reset();
+    }
+
+    fn poll_irq_status(&self) -> bool {
+        !self.irq_sources().is_empty()
+    }
+}
+
+impl Snapshot for Bus<'_> {
+    fn save_state(&self, w: &mut StateWriter) {
+        w.write_bytes(&self.cpu_vram);
+        w.write_usize(self.cycles);
+        w.write_u8(self.open_bus);
+        self.ppu.save_state(w);
+        self.apu.save_state(w);
+        self.joypad1.save_state(w);
+        self.mapper.borrow().save_state(w);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), String> {
+        r.read_into(&mut self.cpu_vram)?;
+        self.cycles = r.read_usize()?;
+        self.open_bus = r.read_u8()?;
+        self.ppu.load_state(r)?;
+        self.apu.load_state(r)?;
+        self.joypad1.load_state(r)?;
+        self.mapper.borrow_mut().load_state(r)
+    }
 }
 
 impl Mem for Bus<'_> {
     fn mem_read(&mut self, addr: u16) -> u8 {
-        match addr {
+        let data = match addr {
             RAM ..= RAM_MIRRORS_END => {
                 let mirror_down_addr = addr & 0b0000111_11111111;
                 self.cpu_vram[mirror_down_addr as usize]
             }
-            0x2000 | 0x2001 | 0x2003 | 0x2005 | 0x2006 | 0x4014 => {
-                panic!("Attempt to read from write-only PPU address {:x}", addr);
-            }
+            // write-only PPU registers read back the PPU's own data bus latch
+            0x2000 | 0x2001 | 0x2003 | 0x2005 | 0x2006 => self.ppu.read_open_bus(),
             0x2002 => self.ppu.read_status(),
             0x2004 => self.ppu.read_oam_data(),
             0x2007 => self.ppu.read_data(),
@@ -83,30 +183,26 @@ impl Mem for Bus<'_> {
                 self.mem_read(mirror_down_addr)
             }
 
-            0x4000..=0x4015 => {
-                //ignore APU 
-                0
-            }
+            // bit 5 isn't driven, and the value is internal to the CPU so the bus keeps its last value
+            0x4015 => return self.apu.read_status() | (self.open_bus & 0b0010_0000),
 
-            0x4016 => {
-                // ignore joypad 1;
-                self.joypad1.read()
-            }
+            // only the low bits are driven by the controllers
+            0x4016 => self.joypad1.read() | (self.open_bus & 0b1110_0000),
 
-            0x4017 => {
-                // ignore joypad 2
-                0
-            }
+            // no second controller
+            0x4017 => self.open_bus & 0b1110_0000,
 
-            0x8000 ..= 0xFFFF => self.read_prg_rom(addr),
-            _ => {
-                println!("Ignoring mem access at 0x{:<04x}", addr);
-                0
-            }
-        }
+            0x4020..=0xFFFF => self.mapper.borrow_mut().cpu_read(addr).unwrap_or(self.open_bus),
+
+            // write-only APU registers, OAM DMA and the unused test registers
+            _ => self.open_bus,
+        };
+        self.open_bus = data;
+        data
     }
 
     fn mem_write(&mut self, addr: u16, data: u8) {
+        self.open_bus = data;
         match addr {
             RAM ..= RAM_MIRRORS_END => {
                 let mirror_down_addr = addr & 0b0000111_11111111;
@@ -118,7 +214,7 @@ impl Mem for Bus<'_> {
             0x2001 => {
                 self.ppu.write_to_mask(data);
             }
-            0x2002 => panic!("attempt to write to PPU status register"),
+            0x2002 => self.ppu.write_to_status(data),
             0x2003 => {
                 self.ppu.write_to_oam_addr(data);
             }
@@ -151,8 +247,12 @@ impl Mem for Bus<'_> {
                 self.apu.write_noise(addr, data);
             }
 
-            0x4010..=0x4013 | 0x4015 => {
-                //ignore APU 
+            0x4010..=0x4013 => {
+                self.apu.write_dmc(addr, data);
+            }
+
+            0x4015 => {
+                self.apu.write_status(data);
             }
 
             0x4016 => {
@@ -160,21 +260,21 @@ impl Mem for Bus<'_> {
             }
 
             0x4017 => {
-                // ignore joypad 2
+                self.apu.write_frame_counter(data);
             }
 
             // https://wiki.nesdev.com/w/index.php/PPU_programmer_reference#OAM_DMA_.28.244014.29_.3E_write
             0x4014 => {
-                let mut buffer: [u8; 256] = [0; 256];
+                // the CPU halts for a cycle, plus one more to line up with a read cycle
+                let align_cycles = if self.cycles % 2 == 1 { 2 } else { 1 };
+                self.tick(align_cycles);
+
+                // then every byte takes a read cycle and a write cycle to $2004
                 let hi: u16 = (data as u16) << 8;
                 for i in 0..256u16 {
-                    buffer[i as usize] = self.mem_read(hi + i);
-                }
-
-                self.ppu.write_oam_dma(&buffer);
-
-                let add_cycles: u16 = if self.cycles % 2 == 1 { 514 } else { 513 };
-                for _ in 0..add_cycles {
+                    let value = self.mem_read(hi + i);
+                    self.tick(1);
+                    self.ppu.write_to_oam_data(value);
                     self.tick(1);
                 }
             }
@@ -183,12 +283,45 @@ impl Mem for Bus<'_> {
                 let mirror_down_addr = addr & 0b00100000_00000111;
                 self.mem_write(mirror_down_addr, data);
             }
-            0x8000 ..= 0xFFFF => {
-                panic!("Attempt to write to Cartridge ROM space")
-            }
-            _ => {
-                println!("Ignoring mem write-access at 0x{:<04x}", addr);
+            0x4020..=0xFFFF => {
+                self.mapper.borrow_mut().cpu_write_at(addr, data, self.cycles);
             }
+            // the unused APU test registers
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apu::sounds::SoundManager;
+    use crate::cartridge::test::ines_image;
+
+    #[test]
+    fn test_irq_line_stays_low_until_every_source_is_acknowledged() {
+        let (_sound, txs) = SoundManager::new(44100.0);
+        let rom = Rom::new(&ines_image(0, 1, 1)).unwrap();
+        let mut bus = Bus::new(rom, |_: &NesPPU, _: &mut Joypad| {}, NesAPU::new(txs));
+        // a one byte DMC sample with its IRQ enabled, and the frame IRQ in 4-step mode
+        bus.mem_write(0x4010, 0x8f);
+        bus.mem_write(0x4013, 0x00);
+        bus.mem_write(0x4015, 0x10);
+        bus.mem_write(0x4017, 0x00);
+        while bus.irq_sources() != IrqSource::FRAME_COUNTER | IrqSource::DMC {
+            bus.tick(1);
+            assert!(bus.cycles() < 40_000, "IRQs not raised");
         }
+        assert!(bus.poll_irq_status());
+
+        // reading $4015 acknowledges the frame IRQ, the DMC still holds the line
+        bus.mem_read(0x4015);
+        assert_eq!(bus.irq_sources(), IrqSource::DMC);
+        assert!(bus.poll_irq_status());
+
+        // writing $4015 acknowledges the DMC IRQ
+        bus.mem_write(0x4015, 0x00);
+        assert!(bus.irq_sources().is_empty());
+        assert!(!bus.poll_irq_status());
     }
-}
\ No newline at end of file
+}
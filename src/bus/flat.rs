@@ -0,0 +1,51 @@
+use crate::cpu::{CpuBus, Mem};
+
+// A bare 6502 system: 64K of RAM, no mapped devices and no interrupts.
+// Used for running plain 6502 programs such as the snake demo and for tests.
+pub struct FlatBus {
+    ram: Box<[u8; 0x10000]>,
+    cycles: usize,
+}
+
+impl FlatBus {
+    pub fn new() -> Self {
+        FlatBus {
+            ram: Box::new([0; 0x10000]),
+            cycles: 0,
+        }
+    }
+
+    pub fn cycles(&self) -> usize {
+        self.cycles
+    }
+}
+
+impl Default for FlatBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Mem for FlatBus {
+    fn mem_read(&mut self, addr: u16) -> u8 {
+        self.ram[addr as usize]
+    }
+
+    fn mem_write(&mut self, addr: u16, data: u8) {
+        self.ram[addr as usize] = data;
+    }
+}
+
+impl CpuBus for FlatBus {
+    fn tick(&mut self, cycles: u8) {
+        self.cycles += cycles as usize;
+    }
+
+    fn poll_nmi_status(&mut self) -> Option<u8> {
+        None
+    }
+
+    fn poll_irq_status(&self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,51 @@
+use std::str::FromStr;
+
+use rand::{rngs::StdRng, RngCore, SeedableRng};
+
+// What CPU RAM holds after power on. Real consoles leave whatever the chips
+// settle on, and some games accidentally depend on it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RamInit {
+    #[default]
+    Zeros,
+    Ones,
+    // $00 x4, $FF x4 repeating, a common power-on state
+    Pattern,
+    Random(u64),
+}
+
+impl RamInit {
+    pub fn fill(&self, ram: &mut [u8]) {
+        match self {
+            RamInit::Zeros => ram.fill(0x00),
+            RamInit::Ones => ram.fill(0xFF),
+            RamInit::Pattern => {
+                for (i, byte) in ram.iter_mut().enumerate() {
+                    *byte = if i & 0b100 == 0 { 0x00 } else { 0xFF };
+                }
+            }
+            RamInit::Random(seed) => StdRng::seed_from_u64(*seed).fill_bytes(ram),
+        }
+    }
+}
+
+// zeros, ff, pattern, random or random:SEED
+impl FromStr for RamInit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "zeros" | "00" => Ok(RamInit::Zeros),
+            "ff" | "ones" => Ok(RamInit::Ones),
+            "pattern" => Ok(RamInit::Pattern),
+            "random" => Ok(RamInit::Random(rand::random())),
+            other => match other.strip_prefix("random:") {
+                Some(seed) => seed
+                    .parse()
+                    .map(RamInit::Random)
+                    .map_err(|err| format!("Invalid RAM seed {}: {}", seed, err)),
+                None => Err(format!("Unknown RAM init {}, expected zeros, ff, pattern or random[:SEED]", s)),
+            },
+        }
+    }
+}
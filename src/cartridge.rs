@@ -1,34 +1,120 @@
+use crate::mapper;
+
 const NES_TAG: [u8; 4] = [0x4e, 0x45, 0x53, 0x1a];
 const PRG_ROM_PAGE_SIZE: usize = 16 * 1024;
 const CHR_ROM_PAGE_SIZE: usize = 8 * 1024;
+const PRG_RAM_PAGE_SIZE: usize = 8 * 1024;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 #[allow(non_camel_case_types)]
 pub enum Mirroring {
    VERTICAL,
    HORIZONTAL,
    FOUR_SCREEN,
+   SINGLE_SCREEN_A,
+   SINGLE_SCREEN_B,
+}
+
+impl Mirroring {
+   // Which 1 KB page of nametable RAM each of the four nametables at $2000-$2FFF uses.
+   // Pages 2 and 3 only exist on four-screen boards, which carry another 2 KB.
+   //
+   // Horizontal:  Vertical:   Single screen:  Four screen:
+   //   [ A ] [ a ]  [ A ] [ B ]  [ A ] [ a ]     [ A ] [ B ]
+   //   [ B ] [ b ]  [ a ] [ b ]  [ a ] [ a ]     [ C ] [ D ]
+   pub fn nametable_pages(&self) -> [u16; 4] {
+      match self {
+         Mirroring::HORIZONTAL => [0, 0, 1, 1],
+         Mirroring::VERTICAL => [0, 1, 0, 1],
+         Mirroring::SINGLE_SCREEN_A => [0, 0, 0, 0],
+         Mirroring::SINGLE_SCREEN_B => [1, 1, 1, 1],
+         Mirroring::FOUR_SCREEN => [0, 1, 2, 3],
+      }
+   }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TimingRegion {
+   Ntsc,
+   Pal,
+   MultiRegion,
+   Dendy,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ConsoleType {
+   Nes,
+   VsSystem { ppu_type: u8, hardware_type: u8 },
+   Playchoice10,
+   Extended(u8),
 }
 
 pub struct Rom {
    pub prg_rom: Vec<u8>,
    pub chr_rom: Vec<u8>,
-   pub mapper: u8,
+   pub mapper: u16,
+   pub submapper: u8,
    pub screen_mirroring: Mirroring,
    pub is_chr_ram: bool,
+   pub has_battery: bool,
+
+   pub is_nes2: bool,
+   pub prg_ram_size: usize,
+   pub prg_nvram_size: usize,
+   pub chr_ram_size: usize,
+   pub chr_nvram_size: usize,
+   pub timing: TimingRegion,
+   pub console_type: ConsoleType,
+   pub misc_rom_count: u8,
+   pub default_expansion_device: u8,
+}
+
+// NES 2.0 ROM sizes: a 12-bit page count, or when the MSB nibble is $F,
+// an exponent-multiplier pair giving 2^E * (MM*2+1) bytes.
+fn nes2_rom_size(lsb: u8, msb_nibble: u8, page_size: usize) -> Result<usize, String> {
+    if msb_nibble == 0x0F {
+        let exponent = (lsb >> 2) as u32;
+        let multiplier = (lsb & 0b11) as usize * 2 + 1;
+        1usize
+            .checked_shl(exponent)
+            .and_then(|size| size.checked_mul(multiplier))
+            .ok_or_else(|| "invalid NES 2.0 ROM size".to_string())
+    } else {
+        Ok(((msb_nibble as usize) << 8 | lsb as usize) * page_size)
+    }
+}
+
+// NES 2.0 RAM sizes: 0 means none, otherwise 64 << shift bytes.
+fn nes2_ram_size(shift: u8) -> usize {
+    if shift == 0 {
+        0
+    } else {
+        64 << shift
+    }
 }
 
 impl Rom {
     pub fn new(raw: &Vec<u8>) -> Result<Rom, String> {
-        if &raw[0..4] != NES_TAG {
+        if raw.len() < 16 || raw[0..4] != NES_TAG {
             return Err("File is not iNES format".to_string())
         }
 
-        let mapper = (raw[7] & 0b1111_0000) | (raw[6] >> 4);
-
         let ines_ver = (raw[7] >> 2) & 0b0000_0011;
-        if ines_ver != 0 {
-            return Err("iNES 2.0 isn't supported".to_string())
+        let is_nes2 = match ines_ver {
+            0 => false,
+            2 => true,
+            _ => return Err(format!("Unknown iNES header version {}", ines_ver)),
+        };
+
+        let mut mapper = ((raw[7] & 0b1111_0000) | (raw[6] >> 4)) as u16;
+        let mut submapper = 0;
+        if is_nes2 {
+            mapper |= ((raw[8] & 0b0000_1111) as u16) << 8;
+            submapper = raw[8] >> 4;
+        }
+
+        if !mapper::is_supported(mapper) {
+            return Err(format!("Unsupported mapper {}", mapper))
         }
 
         let four_screen = raw[6] & 0b0000_1000 != 0;
@@ -39,31 +125,196 @@ impl Rom {
             (false, false) => Mirroring::HORIZONTAL,
         };
 
-        let prg_rom_size = raw[4] as usize * PRG_ROM_PAGE_SIZE;
-        let chr_rom_size = raw[5] as usize * CHR_ROM_PAGE_SIZE;
+        let (prg_rom_size, chr_rom_size) = if is_nes2 {
+            (
+                nes2_rom_size(raw[4], raw[9] & 0b0000_1111, PRG_ROM_PAGE_SIZE)?,
+                nes2_rom_size(raw[5], raw[9] >> 4, CHR_ROM_PAGE_SIZE)?,
+            )
+        } else {
+            (raw[4] as usize * PRG_ROM_PAGE_SIZE, raw[5] as usize * CHR_ROM_PAGE_SIZE)
+        };
 
-        let skip_trainer = raw[6] & 0b0000_0100 != 0;
+        if prg_rom_size == 0 {
+            return Err("ROM image has no PRG ROM".to_string())
+        }
+
+        let (prg_ram_size, prg_nvram_size, chr_ram_size, chr_nvram_size) = if is_nes2 {
+            (
+                nes2_ram_size(raw[10] & 0b0000_1111),
+                nes2_ram_size(raw[10] >> 4),
+                nes2_ram_size(raw[11] & 0b0000_1111),
+                nes2_ram_size(raw[11] >> 4),
+            )
+        } else {
+            // iNES 1.0 gives PRG RAM in 8K units, where 0 still means 8K
+            let prg_ram_size = raw[8].max(1) as usize * PRG_RAM_PAGE_SIZE;
+            let chr_ram_size = if chr_rom_size == 0 { CHR_ROM_PAGE_SIZE } else { 0 };
+            (prg_ram_size, 0, chr_ram_size, 0)
+        };
+
+        let timing = if is_nes2 {
+            match raw[12] & 0b11 {
+                0 => TimingRegion::Ntsc,
+                1 => TimingRegion::Pal,
+                2 => TimingRegion::MultiRegion,
+                _ => TimingRegion::Dendy,
+            }
+        } else if raw[9] & 1 != 0 {
+            TimingRegion::Pal
+        } else {
+            TimingRegion::Ntsc
+        };
+
+        let console_type = match raw[7] & 0b11 {
+            0 => ConsoleType::Nes,
+            1 if is_nes2 => ConsoleType::VsSystem {
+                ppu_type: raw[13] & 0b0000_1111,
+                hardware_type: raw[13] >> 4,
+            },
+            1 => ConsoleType::VsSystem { ppu_type: 0, hardware_type: 0 },
+            2 => ConsoleType::Playchoice10,
+            _ if is_nes2 => ConsoleType::Extended(raw[13] & 0b0000_1111),
+            _ => ConsoleType::Extended(0),
+        };
 
-        let prg_rom_start = 16 + if skip_trainer {512} else {0};
-        let chr_rom_start = prg_rom_start + prg_rom_size;
-
-        if chr_rom_size == 0 {
-            Ok(Rom{
-                prg_rom: raw[prg_rom_start..(prg_rom_start + prg_rom_size)].to_vec(),
-                chr_rom: vec![0; CHR_ROM_PAGE_SIZE],
-                mapper,
-                screen_mirroring,
-                is_chr_ram: true,
-            })
+        let (misc_rom_count, default_expansion_device) = if is_nes2 {
+            (raw[14] & 0b11, raw[15] & 0b0011_1111)
         } else {
-            Ok(Rom{
-                prg_rom: raw[prg_rom_start..(prg_rom_start + prg_rom_size)].to_vec(),
-                chr_rom: raw[chr_rom_start..(chr_rom_start + chr_rom_size)].to_vec(),
-                mapper,
-                screen_mirroring,
-                is_chr_ram: false,
-            })
+            (0, 0)
+        };
+
+        let has_battery = raw[6] & 0b0000_0010 != 0;
+        let skip_trainer = raw[6] & 0b0000_0100 != 0;
+
+        let prg_rom_start: usize = 16 + if skip_trainer {512} else {0};
+        let chr_rom_start = prg_rom_start.checked_add(prg_rom_size);
+        let chr_rom_end = chr_rom_start.and_then(|start| start.checked_add(chr_rom_size));
+        let (chr_rom_start, chr_rom_end) = match (chr_rom_start, chr_rom_end) {
+            (Some(start), Some(end)) => (start, end),
+            _ => return Err("invalid NES 2.0 ROM size".to_string()),
+        };
+
+        if raw.len() < chr_rom_end {
+            return Err("ROM image is truncated".to_string())
         }
+
+        let is_chr_ram = chr_rom_size == 0;
+        let chr_rom = if is_chr_ram {
+            let size = chr_ram_size + chr_nvram_size;
+            vec![0; if size == 0 { CHR_ROM_PAGE_SIZE } else { size }]
+        } else {
+            raw[chr_rom_start..chr_rom_end].to_vec()
+        };
+
+        Ok(Rom{
+            prg_rom: raw[prg_rom_start..chr_rom_start].to_vec(),
+            chr_rom,
+            mapper,
+            submapper,
+            screen_mirroring,
+            is_chr_ram,
+            has_battery,
+
+            is_nes2,
+            prg_ram_size,
+            prg_nvram_size,
+            chr_ram_size,
+            chr_nvram_size,
+            timing,
+            console_type,
+            misc_rom_count,
+            default_expansion_device,
+        })
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    // iNES 1.0 image where every PRG and CHR byte holds the number of the
+    // 1K block it sits in, so tests can tell which bank is mapped where.
+    pub fn ines_image(mapper: u8, prg_pages: u8, chr_pages: u8) -> Vec<u8> {
+        let mut raw = vec![0x4e, 0x45, 0x53, 0x1a, prg_pages, chr_pages, mapper << 4, mapper & 0xf0, 0, 0, 0, 0, 0, 0, 0, 0];
+        let prg_size = prg_pages as usize * super::PRG_ROM_PAGE_SIZE;
+        let chr_size = chr_pages as usize * super::CHR_ROM_PAGE_SIZE;
+        raw.extend((0..prg_size).map(|i| (i / 1024) as u8));
+        raw.extend((0..chr_size).map(|i| (i / 1024) as u8));
+        raw
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test::ines_image;
+
+    fn nes2_header(prg_lsb: u8, chr_lsb: u8, size_msb: u8) -> Vec<u8> {
+        let mut raw = ines_image(0, 0, 0);
+        raw[4] = prg_lsb;
+        raw[5] = chr_lsb;
+        raw[7] |= 0b0000_1000;
+        raw[9] = size_msb;
+        raw
+    }
+
+    #[test]
+    fn test_ines_header() {
+        let mut raw = ines_image(66, 2, 1);
+        raw[6] |= 0b0000_0011;
+        let rom = Rom::new(&raw).unwrap();
+        assert!(!rom.is_nes2);
+        assert_eq!(rom.mapper, 66);
+        assert_eq!(rom.prg_rom.len(), 32 * 1024);
+        assert_eq!(rom.chr_rom.len(), 8 * 1024);
+        assert_eq!(rom.prg_rom[0x4000], 16);
+        assert_eq!(rom.screen_mirroring, Mirroring::VERTICAL);
+        assert!(rom.has_battery);
+        assert!(!rom.is_chr_ram);
+        assert_eq!(rom.prg_ram_size, 8 * 1024);
+    }
+
+    #[test]
+    fn test_nes2_page_count() {
+        // a 12-bit page count, $101 pages of PRG
+        let mut raw = nes2_header(0x01, 0x00, 0x01);
+        raw[11] = 0x07;
+        raw.resize(16 + 0x101 * PRG_ROM_PAGE_SIZE, 0);
+        let rom = Rom::new(&raw).unwrap();
+        assert!(rom.is_nes2);
+        assert_eq!(rom.prg_rom.len(), 0x101 * PRG_ROM_PAGE_SIZE);
+        assert!(rom.is_chr_ram);
+        assert_eq!(rom.chr_ram_size, 64 << 7);
+        assert_eq!(rom.chr_rom.len(), 64 << 7);
+    }
+
+    #[test]
+    fn test_nes2_exponent_size() {
+        // 2^13 * (1*2+1) bytes of PRG, 2^10 * 1 of CHR
+        let mut raw = nes2_header(13 << 2 | 1, 10 << 2, 0xFF);
+        raw.resize(16 + 3 * 8192 + 1024, 0);
+        let rom = Rom::new(&raw).unwrap();
+        assert_eq!(rom.prg_rom.len(), 3 * 8192);
+        assert_eq!(rom.chr_rom.len(), 1024);
+    }
+
+    #[test]
+    fn test_nes2_size_overflow() {
+        let raw = nes2_header(63 << 2 | 3, 0, 0x0F);
+        assert_eq!(Rom::new(&raw).err(), Some("invalid NES 2.0 ROM size".to_string()));
+        // each size fits on its own, but not together
+        let raw = nes2_header(62 << 2 | 1, 62 << 2 | 1, 0xFF);
+        assert_eq!(Rom::new(&raw).err(), Some("invalid NES 2.0 ROM size".to_string()));
+    }
+
+    #[test]
+    fn test_no_prg_rom() {
+        assert_eq!(Rom::new(&ines_image(0, 0, 1)).err(), Some("ROM image has no PRG ROM".to_string()));
+        assert_eq!(Rom::new(&nes2_header(0, 1, 0)).err(), Some("ROM image has no PRG ROM".to_string()));
+    }
+
+    #[test]
+    fn test_truncated_image() {
+        let raw = ines_image(0, 2, 1);
+        assert_eq!(Rom::new(&raw[..10].to_vec()).err(), Some("File is not iNES format".to_string()));
+        assert_eq!(Rom::new(&raw[..raw.len() - 1].to_vec()).err(), Some("ROM image is truncated".to_string()));
+    }
+}
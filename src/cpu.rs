@@ -1,6 +1,7 @@
-use crate::bus::Bus;
+use crate::bus::FlatBus;
 use crate::interrupt;
-use crate::opcodes::OPCODE_MAP;
+use crate::opcodes::{Instruction, OPCODE_TABLE};
+use crate::savestate::{Snapshot, StateReader, StateWriter};
 
 pub const CPU_FREQ: f32 = 1789773.0;
 
@@ -33,14 +34,24 @@ bitflags!{
     }
 }
 
-pub struct CPU<'a> {
+// Reported once the CPU has locked up on a JAM opcode. Only a reset gets it going again.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CpuHalted {
+    pub pc: u16,
+    pub opcode: u8,
+}
+
+pub struct CPU<B> {
     pub reg_a: u8,
     pub reg_x: u8,
     pub reg_y: u8,
     pub stack_pointer: u8,
     pub status: CpuFlags,
     pub program_counter: u16,
-    pub bus: Bus<'a>,
+    pub bus: B,
+    // I flag as last seen by the interrupt poll at the end of an instruction
+    irq_inhibit: bool,
+    halted: Option<CpuHalted>,
 }
 
 pub trait Mem {
@@ -49,7 +60,7 @@ pub trait Mem {
 
     fn mem_read_u16(&mut self, pos: u16) -> u16 {
         let low = self.mem_read(pos) as u16;
-        let high = self.mem_read(pos + 1) as u16;
+        let high = self.mem_read(pos.wrapping_add(1)) as u16;
         (high << 8) | low
     }
 
@@ -57,30 +68,76 @@ pub trait Mem {
         let high = (data >> 8) as u8;
         let low = (data & 0xFF) as u8;
         self.mem_write(pos, low);
-        self.mem_write(pos + 1, high)
+        self.mem_write(pos.wrapping_add(1), high)
     }
 }
 
-impl Mem for CPU<'_> {
+// Everything the CPU needs from the machine around it: memory, a clock to
+// advance by the cycles each access takes, and the interrupt lines.
+pub trait CpuBus: Mem {
+    fn tick(&mut self, cycles: u8);
+    fn poll_nmi_status(&mut self) -> Option<u8>;
+    fn poll_irq_status(&self) -> bool;
+
+    // The rest of the machine's part when power comes on or the reset button is pressed.
+    fn power_on(&mut self) {}
+    fn reset(&mut self) {}
+}
+
+// Every access the CPU makes takes one cycle, so the rest of the machine is
+// advanced as the instruction goes rather than after it.
+impl<B: CpuBus> Mem for CPU<B> {
     fn mem_read(&mut self, addr: u16) -> u8 {
-        self.bus.mem_read(addr)
+        let data = self.bus.mem_read(addr);
+        self.bus.tick(1);
+        data
     }
 
     fn mem_write(&mut self, addr: u16, data: u8) {
-        self.bus.mem_write(addr, data)
-    }
-
-    fn mem_read_u16(&mut self, pos: u16) -> u16 {
-        self.bus.mem_read_u16(pos)
+        self.bus.mem_write(addr, data);
+        self.bus.tick(1);
     }
+}
 
-    fn mem_write_u16(&mut self, pos: u16, data: u16) {
-        self.bus.mem_write_u16(pos, data)
+impl<B: CpuBus + Snapshot> Snapshot for CPU<B> {
+    fn save_state(&self, w: &mut StateWriter) {
+        w.write_u8(self.reg_a);
+        w.write_u8(self.reg_x);
+        w.write_u8(self.reg_y);
+        w.write_u8(self.stack_pointer);
+        w.write_u8(self.status.bits());
+        w.write_u16(self.program_counter);
+        w.write_bool(self.irq_inhibit);
+        w.write_bool(self.halted.is_some());
+        if let Some(halted) = self.halted {
+            w.write_u16(halted.pc);
+            w.write_u8(halted.opcode);
+        }
+        self.bus.save_state(w);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), String> {
+        self.reg_a = r.read_u8()?;
+        self.reg_x = r.read_u8()?;
+        self.reg_y = r.read_u8()?;
+        self.stack_pointer = r.read_u8()?;
+        self.status = CpuFlags::from_bits_truncate(r.read_u8()?);
+        self.program_counter = r.read_u16()?;
+        self.irq_inhibit = r.read_bool()?;
+        self.halted = if r.read_bool()? {
+            Some(CpuHalted {
+                pc: r.read_u16()?,
+                opcode: r.read_u8()?,
+            })
+        } else {
+            None
+        };
+        self.bus.load_state(r)
     }
 }
 
-impl<'a> CPU<'a> {
-    pub fn new(bus: Bus<'_>) -> CPU<'_> {
+impl<B: CpuBus> CPU<B> {
+    pub fn new(bus: B) -> Self {
         CPU {
             reg_a: 0,
             reg_x: 0,
@@ -89,6 +146,8 @@ impl<'a> CPU<'a> {
             status: CpuFlags::from_bits_truncate(0b100100),
             program_counter: 0,
             bus,
+            irq_inhibit: true,
+            halted: None,
         }
     }
 
@@ -115,30 +174,46 @@ impl<'a> CPU<'a> {
         high << 8 | low
     }
 
-    pub fn load_and_run(&mut self, program: Vec<u8>) {
-        self.load(program);
-        self.reset();
-        self.run();
+    // Pulls spend a cycle incrementing S first, reading the stack while at it.
+    fn stack_dummy_read(&mut self) {
+        self.mem_read(0x0100u16 + self.stack_pointer as u16);
     }
 
-    pub fn reset(&mut self) {
+    // Registers start out cleared, then the CPU goes through the reset sequence,
+    // which leaves the stack pointer at $FD.
+    pub fn power_on(&mut self) {
+        self.bus.power_on();
         self.reg_a = 0;
         self.reg_x = 0;
         self.reg_y = 0;
-        self.stack_pointer = 0xfd;
-        self.status = CpuFlags::from_bits_truncate(0b0010_0100);
+        self.stack_pointer = 0;
+        self.status = CpuFlags::from_bits_truncate(0b0010_0000);
+        self.reset_sequence();
+    }
 
-        self.program_counter = self.mem_read_u16(0xFFFC)
+    // The reset button keeps A, X, Y and the rest of the flags.
+    pub fn soft_reset(&mut self) {
+        self.bus.reset();
+        self.reset_sequence();
     }
 
-    pub fn load(&mut self, program: Vec<u8>) {
-        for i in 0..(program.len() as u16) {
-            self.mem_write(0x0600 + i, program[i as usize])
-        }
-        self.mem_write_u16(0xFFFC, 0x0600);
+    // Reset runs like an interrupt with the stack writes turned into reads,
+    // so S still moves down by 3. It takes 5 cycles before fetching the vector, 7 in all.
+    fn reset_sequence(&mut self) {
+        self.status.insert(CpuFlags::INTERRUPT_DISABLE);
+        self.irq_inhibit = true;
+        self.halted = None;
+
+        self.bus.tick(2);
+        self.stack_pointer = self.stack_pointer.wrapping_sub(3);
+        self.bus.tick(3);
+        self.program_counter = self.mem_read_u16(0xFFFC);
     }
 
     fn interrupt(&mut self, interrupt: interrupt::Interrupt) {
+        for _ in 0..interrupt.dummy_reads {
+            self.mem_read(self.program_counter);
+        }
         self.stack_push_u16(self.program_counter);
         let mut flag = self.status;
         flag.set(CpuFlags::BREAK, interrupt.b_flag_mask & 0b010000 != 0);
@@ -147,7 +222,6 @@ impl<'a> CPU<'a> {
         self.stack_push(flag.bits);
         self.status.insert(CpuFlags::INTERRUPT_DISABLE);
 
-        self.bus.tick(interrupt.cpu_cycles);
         self.program_counter = self.mem_read_u16(interrupt.vector_addr);
     }
 
@@ -157,424 +231,463 @@ impl<'a> CPU<'a> {
 
     pub fn run_with_callback<F>(&mut self, mut callback: F)
     where
-        F: FnMut(&mut CPU),
+        F: FnMut(&mut CPU<B>),
     {
         loop {
-            if let Some(_nmi) = self.bus.poll_nmi_status() {
-                self.interrupt(interrupt::NMI)
-            }
-
+            self.poll_interrupts();
             callback(self);
+            self.execute();
+        }
+    }
 
-            let code = self.mem_read(self.program_counter);
-            self.program_counter += 1;
-            let pc_state = self.program_counter;
+    // Services a pending interrupt, if any, and runs the next instruction.
+    // A halted CPU runs nothing, but the rest of the machine keeps going one cycle per step.
+    pub fn step(&mut self) {
+        self.poll_interrupts();
+        self.execute();
+    }
 
-            let opcode = OPCODE_MAP.get(&code).expect(&format!("OpCode {:x} is not recognized", code));
+    pub fn halted(&self) -> Option<CpuHalted> {
+        self.halted
+    }
 
-            match opcode.name {
-                "ADC" => {
-                    self.adc(&opcode.mode);
-                }
-                "AND" => self.and(&opcode.mode),
-                "ASL" => {
-                    self.asl(&opcode.mode);
-                }
-                "BIT" => {
-                    self.bit(&opcode.mode);
-                }
-                "BCC" => {
-                    self.branch(!self.status.contains(CpuFlags::CARRY));
-                }
-                "BCS" => {
-                    self.branch(self.status.contains(CpuFlags::CARRY));
-                }
-                "BEQ" => {
-                    self.branch(self.status.contains(CpuFlags::ZERO));
-                }
-                "BMI" => {
-                    self.branch(self.status.contains(CpuFlags::NEGATIV));
-                }
-                "BNE" => {
-                    self.branch(!self.status.contains(CpuFlags::ZERO));
-                }
-                "BPL" => {
-                    self.branch(!self.status.contains(CpuFlags::NEGATIV));
-                }
-                "BVC" => {
-                    self.branch(!self.status.contains(CpuFlags::OVERFLOW));
-                }
-                "BVS" => {
-                    self.branch(self.status.contains(CpuFlags::OVERFLOW));
-                }
-                "CLC" => {
-                    self.clc();
-                }
-                "CLD" => {
-                    self.cld();
-                }
-                "CLI" => {
-                    self.cli();
-                }
-                "CLV" => {
-                    self.clv();
-                }
-                "CMP" => {
-                    self.cmp(&opcode.mode);
-                }
-                "CPX" => {
-                    self.cpx(&opcode.mode);
-                }
-                "CPY" => {
-                    self.cpy(&opcode.mode);
-                }
-                "DEC" => {
-                    self.dec(&opcode.mode);
-                }
-                "DEX" => self.dex(),
-                "DEY" => self.dey(),
-                "EOR" => {
-                    self.eor(&opcode.mode);
-                }
-                "INC" => {
-                    self.inc(&opcode.mode);
-                }
-                "INX" => self.inx(),
-                "INY" => self.iny(),
-                "JMP" => {
-                    self.jmp(&opcode.mode);
-                }
-                "JSR" => {
-                    self.jsr();
-                }
-                "LDA" => {
-                    self.lda(&opcode.mode);
-                }
-                "LDX" => {
-                    self.ldx(&opcode.mode);
-                }
-                "LDY" => {
-                    self.ldy(&opcode.mode);
-                }
-                "LSR" => {
-                    self.lsr(&opcode.mode);
-                }
-                "NOP" => {}
-                "ORA" => {
-                    self.ora(&opcode.mode);
-                }
-                "PHA" => {
-                    self.pha();
-                }
-                "PHP" => {
-                    self.php();
-                }
-                "PLA" => {
-                    self.pla();
-                }
-                "PLP" => {
-                    self.plp();
-                }
-                "ROL" => {
-                    self.rol(&opcode.mode);
-                }
-                "ROR" => {
-                    self.ror(&opcode.mode);
-                }
-                "RTS" => {
-                    self.rts();
-                }
-                "RTI" => {
-                    self.rti();
-                }
-                "SBC" => {
-                    self.sbc(&opcode.mode);
-                }
-                "SEC" => {
-                    self.sec();
-                }
-                "SED" => {
-                    self.sed();
-                }
-                "SEI" => {
-                    self.sei();
-                }
-                "STA" => {
-                    self.sta(&opcode.mode);
-                }
-                "STX" => {
-                    self.stx(&opcode.mode);
-                }
-                "STY" => self.sty(&opcode.mode),
-                "TAX" => {
-                    self.tax();
-                }
-                "TAY" => {
-                    self.tay();
-                }
-                "TSX" => {
-                    self.tsx();
-                }
-                "TXA" => {
-                    self.txa();
-                }
-                "TXS" => {
-                    self.txs();
-                }
-                "TYA" => {
-                    self.tya();
-                }
-                "BRK" => {
-                    if !self.status.contains(CpuFlags::INTERRUPT_DISABLE) {
-                        self.interrupt(interrupt::BRK);
-                    }
-                }
-                /* unofficial */
-                "ALR" => {
-                    let (addr, _) = self.get_operand_address(&opcode.mode);
-                    let data = self.mem_read(addr);
-                    self.reg_a &= data;
-                    if self.reg_a & 0b0000_0001 != 0 {
-                        self.status.insert(CpuFlags::CARRY);
-                    } else {
-                        self.status.remove(CpuFlags::CARRY);
-                    }
-                    self.reg_a >>= 1;
-                    self.update_zero_n_negative_flag(self.reg_a)
-                }
-                "*ANC" => {
-                    let (addr, _) = self.get_operand_address(&opcode.mode);
-                    let data = self.mem_read(addr);
-                    self.reg_a &= data;
-                    if self.status.contains(CpuFlags::NEGATIV) {
-                        self.status.insert(CpuFlags::CARRY);
-                    } else {
-                        self.status.remove(CpuFlags::CARRY);
-                    }
-                    self.update_zero_n_negative_flag(self.reg_a);
-                }
-                "*ARR" => {
-                    let (addr, _) = self.get_operand_address(&opcode.mode);
-                    let data = self.mem_read(addr);
-                    self.reg_a &= data;
-                    let carry = self.status.contains(CpuFlags::CARRY);
-                    if self.reg_a & 0b0000_0001 != 0 {
-                        self.status.insert(CpuFlags::CARRY);
-                    } else {
-                        self.status.remove(CpuFlags::CARRY);
-                    }
-                    self.reg_a >>= 1;
-                    if carry {
-                        self.reg_a |= 0b1000_0000;
-                    } //todo: registers
-                    let result = self.reg_a;
-                    let bit_5 = (result >> 5) & 1;
-                    let bit_6 = (result >> 6) & 1;
-
-                    if bit_6 == 1 {
-                        self.status.insert(CpuFlags::CARRY);
-                    } else {
-                        self.status.remove(CpuFlags::CARRY);
-                    }
+    fn poll_interrupts(&mut self) {
+        if self.halted.is_some() {
+            return;
+        }
+        if let Some(_nmi) = self.bus.poll_nmi_status() {
+            self.interrupt(interrupt::NMI)
+        } else if self.bus.poll_irq_status() && !self.irq_inhibit {
+            self.interrupt(interrupt::IRQ)
+        }
+    }
 
-                    if bit_5 ^ bit_6 == 1 {
-                        self.status.insert(CpuFlags::OVERFLOW);
-                    } else {
-                        self.status.remove(CpuFlags::OVERFLOW);
-                    }
+    fn execute(&mut self) {
+        if self.halted.is_some() {
+            self.bus.tick(1);
+            return;
+        }
 
-                    self.update_zero_n_negative_flag(result);
-                }
-                "*AXS" => {
-                    let (addr, _) = self.get_operand_address(&opcode.mode);
-                    let data = self.mem_read(addr);
-                    let x_and_a = self.reg_x & self.reg_a;
-                    let result = x_and_a.wrapping_sub(data);
-
-                    if data <= x_and_a {
-                        self.status.insert(CpuFlags::CARRY);
-                    }
-                    self.update_zero_n_negative_flag(result);
+        let code = self.mem_read(self.program_counter);
+        self.program_counter = self.program_counter.wrapping_add(1);
+        let pc_state = self.program_counter;
 
-                    self.reg_x = result;
-                }
-                "*LAX" => {
-                    let (addr, _) = self.get_operand_address(&opcode.mode);
-                    let data = self.mem_read(addr);
-                    self.reg_a = data;
-                    self.reg_x = self.reg_a;
-                    self.update_zero_n_negative_flag(data);
-                }
-                "*SAX" => {
-                    let data = self.reg_a & self.reg_x;
-                    let (addr, _) = self.get_operand_address(&opcode.mode);
-                    self.mem_write(addr, data);
-                }
-                "*DCP" => {
-                    let (addr, _) = self.get_operand_address(&opcode.mode);
-                    let mut data = self.mem_read(addr);
-                    data = data.wrapping_sub(1);
-                    self.mem_write(addr, data);
-                    // self._update_zero_and_negative_flags(data);
-                    if data <= self.reg_a {
-                        self.status.insert(CpuFlags::CARRY);
-                    }
-                    self.update_zero_n_negative_flag(self.reg_a.wrapping_sub(data));
+        let opcode = OPCODE_TABLE[code as usize];
+        let interrupt_disable = self.status.contains(CpuFlags::INTERRUPT_DISABLE);
 
+        // one-byte instructions still read the byte after the opcode
+        if opcode.len == 1 {
+            self.mem_read(self.program_counter);
+        }
+
+        match opcode.instruction {
+            Instruction::ADC => {
+                self.adc(&opcode.mode);
+            }
+            Instruction::AND => self.and(&opcode.mode),
+            Instruction::ASL => {
+                self.asl(&opcode.mode);
+            }
+            Instruction::BIT => {
+                self.bit(&opcode.mode);
+            }
+            Instruction::BCC => {
+                self.branch(!self.status.contains(CpuFlags::CARRY));
+            }
+            Instruction::BCS => {
+                self.branch(self.status.contains(CpuFlags::CARRY));
+            }
+            Instruction::BEQ => {
+                self.branch(self.status.contains(CpuFlags::ZERO));
+            }
+            Instruction::BMI => {
+                self.branch(self.status.contains(CpuFlags::NEGATIV));
+            }
+            Instruction::BNE => {
+                self.branch(!self.status.contains(CpuFlags::ZERO));
+            }
+            Instruction::BPL => {
+                self.branch(!self.status.contains(CpuFlags::NEGATIV));
+            }
+            Instruction::BVC => {
+                self.branch(!self.status.contains(CpuFlags::OVERFLOW));
+            }
+            Instruction::BVS => {
+                self.branch(self.status.contains(CpuFlags::OVERFLOW));
+            }
+            Instruction::CLC => {
+                self.clc();
+            }
+            Instruction::CLD => {
+                self.cld();
+            }
+            Instruction::CLI => {
+                self.cli();
+            }
+            Instruction::CLV => {
+                self.clv();
+            }
+            Instruction::CMP => {
+                self.cmp(&opcode.mode);
+            }
+            Instruction::CPX => {
+                self.cpx(&opcode.mode);
+            }
+            Instruction::CPY => {
+                self.cpy(&opcode.mode);
+            }
+            Instruction::DEC => {
+                self.dec(&opcode.mode);
+            }
+            Instruction::DEX => self.dex(),
+            Instruction::DEY => self.dey(),
+            Instruction::EOR => {
+                self.eor(&opcode.mode);
+            }
+            Instruction::INC => {
+                self.inc(&opcode.mode);
+            }
+            Instruction::INX => self.inx(),
+            Instruction::INY => self.iny(),
+            Instruction::JMP => {
+                self.jmp(&opcode.mode);
+            }
+            Instruction::JSR => {
+                self.jsr();
+            }
+            Instruction::LDA => {
+                self.lda(&opcode.mode);
+            }
+            Instruction::LDX => {
+                self.ldx(&opcode.mode);
+            }
+            Instruction::LDY => {
+                self.ldy(&opcode.mode);
+            }
+            Instruction::LSR => {
+                self.lsr(&opcode.mode);
+            }
+            Instruction::ORA => {
+                self.ora(&opcode.mode);
+            }
+            Instruction::PHA => {
+                self.pha();
+            }
+            Instruction::PHP => {
+                self.php();
+            }
+            Instruction::PLA => {
+                self.pla();
+            }
+            Instruction::PLP => {
+                self.plp();
+            }
+            Instruction::ROL => {
+                self.rol(&opcode.mode);
+            }
+            Instruction::ROR => {
+                self.ror(&opcode.mode);
+            }
+            Instruction::RTS => {
+                self.rts();
+            }
+            Instruction::RTI => {
+                self.rti();
+            }
+            Instruction::SBC => {
+                self.sbc(&opcode.mode);
+            }
+            Instruction::SEC => {
+                self.sec();
+            }
+            Instruction::SED => {
+                self.sed();
+            }
+            Instruction::SEI => {
+                self.sei();
+            }
+            Instruction::STA => {
+                self.sta(&opcode.mode);
+            }
+            Instruction::STX => {
+                self.stx(&opcode.mode);
+            }
+            Instruction::STY => self.sty(&opcode.mode),
+            Instruction::TAX => {
+                self.tax();
+            }
+            Instruction::TAY => {
+                self.tay();
+            }
+            Instruction::TSX => {
+                self.tsx();
+            }
+            Instruction::TXA => {
+                self.txa();
+            }
+            Instruction::TXS => {
+                self.txs();
+            }
+            Instruction::TYA => {
+                self.tya();
+            }
+            Instruction::JAM => {
+                self.halted = Some(CpuHalted {
+                    pc: pc_state.wrapping_sub(1),
+                    opcode: code,
+                });
+            }
+            Instruction::BRK => {
+                // the byte after BRK is skipped over, so the return address is one past it
+                self.program_counter = self.program_counter.wrapping_add(1);
+                self.interrupt(interrupt::BRK);
+            }
+            /* unofficial */
+            Instruction::ALR => {
+                let addr = self.get_operand_address(&opcode.mode);
+                let data = self.mem_read(addr);
+                self.reg_a &= data;
+                if self.reg_a & 0b0000_0001 != 0 {
+                    self.status.insert(CpuFlags::CARRY);
+                } else {
+                    self.status.remove(CpuFlags::CARRY);
                 }
-                "*ISB" => {
-                    let base_data = self.inc(&opcode.mode);
-
-                    let data = (base_data as i8).wrapping_neg().wrapping_sub(1) as u8;
-
-                    let carry = self.status.contains(CpuFlags::CARRY);
-            
-                    let result = self.reg_a as u16 + data as u16 + carry as u16;
-            
-                    if result > 0xff {
-                        self.sec();
-                    } else {
-                        self.clc();
-                    }
-            
-                    if (self.reg_a ^ result as u8) & (data ^ result as u8) & 0x80 != 0 {
-                        self.status.insert(CpuFlags::OVERFLOW);
-                    } else {
-                        self.status.remove(CpuFlags::OVERFLOW);
-                    }
-            
-                    self.reg_a = result as u8;
-                    self.update_zero_n_negative_flag(self.reg_a);
-                }
-                "*RLA" => {
-                    let data = self.rol(&opcode.mode);
-                    self.reg_a &= data;
-                    self.update_zero_n_negative_flag(self.reg_a);
-                }
-                "*RRA" => {
-                    let data = self.ror(&opcode.mode);
-                    let carry = self.status.contains(CpuFlags::CARRY);
-
-                    let result = self.reg_a as u16 + data as u16 + carry as u16;
-            
-                    if result > 0xff {
-                        self.sec();
-                    } else {
-                        self.clc();
-                    }
-            
-                    if (self.reg_a ^ result as u8) & (data ^ result as u8) & 0x80 != 0 {
-                        self.status.insert(CpuFlags::OVERFLOW);
-                    } else {
-                        self.status.remove(CpuFlags::OVERFLOW);
-                    }
-            
-                    self.reg_a = result as u8;
-                    self.update_zero_n_negative_flag(self.reg_a);
-                }
-                "*SLO" => {
-                    let data = self.asl(&opcode.mode);
-                    self.reg_a |= data;
-                    self.update_zero_n_negative_flag(self.reg_a);
-                }
-                "*SRE" => {
-                    let data = self.lsr(&opcode.mode);
-                    self.reg_a ^= data;
-                    self.update_zero_n_negative_flag(self.reg_a);
+                self.reg_a >>= 1;
+                self.update_zero_n_negative_flag(self.reg_a)
+            }
+            Instruction::ANC => {
+                let addr = self.get_operand_address(&opcode.mode);
+                let data = self.mem_read(addr);
+                self.reg_a &= data;
+                // carry is bit 7 of the result, as if it had been shifted out by ASL
+                if self.reg_a & 0b1000_0000 != 0 {
+                    self.status.insert(CpuFlags::CARRY);
+                } else {
+                    self.status.remove(CpuFlags::CARRY);
                 }
-                "*SBC" => {
-                    self.sbc(&opcode.mode);
+                self.update_zero_n_negative_flag(self.reg_a);
+            }
+            Instruction::ARR => {
+                let addr = self.get_operand_address(&opcode.mode);
+                let data = self.mem_read(addr);
+                self.reg_a &= data;
+                let carry = self.status.contains(CpuFlags::CARRY);
+                if self.reg_a & 0b0000_0001 != 0 {
+                    self.status.insert(CpuFlags::CARRY);
+                } else {
+                    self.status.remove(CpuFlags::CARRY);
                 }
-                "*NOP" => {
-                    match opcode.mode {
-                        AddressingMode::Immediate => {
-                            /* 2 byte NOP (immediate ) */
-                            // todo: might be worth doing the read
-                        }
-                        AddressingMode::NoneAddressing => { /* do nothing */ }
-                        _ => {
-                            let (addr, page_cross) = self.get_operand_address(&opcode.mode);
-                            let _data = self.mem_read(addr);
-                            /* do nothing */
-                            if page_cross {
-                                self.bus.tick(1);
-                            }
-                        }
-                    }
+                self.reg_a >>= 1;
+                if carry {
+                    self.reg_a |= 0b1000_0000;
+                } //todo: registers
+                let result = self.reg_a;
+                let bit_5 = (result >> 5) & 1;
+                let bit_6 = (result >> 6) & 1;
+
+                if bit_6 == 1 {
+                    self.status.insert(CpuFlags::CARRY);
+                } else {
+                    self.status.remove(CpuFlags::CARRY);
                 }
-                "*LXA" => {
-                    self.lda(&opcode.mode);
-                    self.tax();
+
+                if bit_5 ^ bit_6 == 1 {
+                    self.status.insert(CpuFlags::OVERFLOW);
+                } else {
+                    self.status.remove(CpuFlags::OVERFLOW);
                 }
-                "*XAA" => {
-                    self.reg_a = self.reg_x;
-                    self.update_zero_n_negative_flag(self.reg_a);
-                    let (addr, _) = self.get_operand_address(&opcode.mode);
-                    let data = self.mem_read(addr);
-                    self.reg_a &= data;
-                    self.update_zero_n_negative_flag(self.reg_a);
+
+                self.update_zero_n_negative_flag(result);
+            }
+            Instruction::AXS => {
+                let addr = self.get_operand_address(&opcode.mode);
+                let data = self.mem_read(addr);
+                let x_and_a = self.reg_x & self.reg_a;
+                let result = x_and_a.wrapping_sub(data);
+
+                if data <= x_and_a {
+                    self.status.insert(CpuFlags::CARRY);
+                } else {
+                    self.status.remove(CpuFlags::CARRY);
                 }
-                "*LAS" => {
-                    let (addr, _) = self.get_operand_address(&opcode.mode);
-                    let mut data = self.mem_read(addr);
-                    data = data & self.stack_pointer;
-                    self.reg_a = data;
-                    self.reg_x = data;
-                    self.stack_pointer = data;
-                    self.update_zero_n_negative_flag(data);
+                self.update_zero_n_negative_flag(result);
+
+                self.reg_x = result;
+            }
+            Instruction::LAX => {
+                let addr = self.get_operand_address(&opcode.mode);
+                let data = self.mem_read(addr);
+                self.reg_a = data;
+                self.reg_x = self.reg_a;
+                self.update_zero_n_negative_flag(data);
+            }
+            Instruction::SAX => {
+                let data = self.reg_a & self.reg_x;
+                let addr = self.get_store_address(&opcode.mode);
+                self.mem_write(addr, data);
+            }
+            Instruction::DCP => {
+                let data = self.read_modify_write(&opcode.mode, |_, data| data.wrapping_sub(1));
+                // self._update_zero_and_negative_flags(data);
+                if data <= self.reg_a {
+                    self.status.insert(CpuFlags::CARRY);
+                } else {
+                    self.status.remove(CpuFlags::CARRY);
                 }
-                "*TAS" => {
-                    let data = self.reg_a & self.reg_x;
-                    self.stack_pointer = data;
-                    let mem_address = self.mem_read_u16(self.program_counter) + self.reg_y as u16;
+                self.update_zero_n_negative_flag(self.reg_a.wrapping_sub(data));
+
+            }
+            Instruction::ISB => {
+                let base_data = self.inc(&opcode.mode);
+
+                let data = (base_data as i8).wrapping_neg().wrapping_sub(1) as u8;
 
-                    let data = ((mem_address >> 8) as u8 + 1) & self.stack_pointer;
-                    self.mem_write(mem_address, data)
+                let carry = self.status.contains(CpuFlags::CARRY);
+        
+                let result = self.reg_a as u16 + data as u16 + carry as u16;
+        
+                if result > 0xff {
+                    self.sec();
+                } else {
+                    self.clc();
                 }
-                "*AHX" => match opcode.mode {
-                    AddressingMode::Indirect_Y => {
-                        let pos: u8 = self.mem_read(self.program_counter);
-                        let mem_address = self.mem_read_u16(pos as u16) + self.reg_y as u16;
-                        let data = self.reg_a & self.reg_x & (mem_address >> 8) as u8;
-                        self.mem_write(mem_address, data)
-                    }
-                    AddressingMode::Absolute_Y => {
-                        let mem_address =
-                            self.mem_read_u16(self.program_counter) + self.reg_y as u16;
+        
+                if (self.reg_a ^ result as u8) & (data ^ result as u8) & 0x80 != 0 {
+                    self.status.insert(CpuFlags::OVERFLOW);
+                } else {
+                    self.status.remove(CpuFlags::OVERFLOW);
+                }
+        
+                self.reg_a = result as u8;
+                self.update_zero_n_negative_flag(self.reg_a);
+            }
+            Instruction::RLA => {
+                let data = self.rol(&opcode.mode);
+                self.reg_a &= data;
+                self.update_zero_n_negative_flag(self.reg_a);
+            }
+            Instruction::RRA => {
+                let data = self.ror(&opcode.mode);
+                let carry = self.status.contains(CpuFlags::CARRY);
 
-                        let data = self.reg_a & self.reg_x & (mem_address >> 8) as u8;
-                        self.mem_write(mem_address, data)
-                    }
-                    _ => {}
+                let result = self.reg_a as u16 + data as u16 + carry as u16;
+        
+                if result > 0xff {
+                    self.sec();
+                } else {
+                    self.clc();
                 }
-                "*SHX" => {
-                    let mem_address = self.mem_read_u16(self.program_counter) + self.reg_y as u16;
-
-                    // todo if cross page boundry {
-                    //     mem_address &= (self.x as u16) << 8;
-                    // }
-                    let data = self.reg_x & ((mem_address >> 8) as u8 + 1);
-                    self.mem_write(mem_address, data)
+        
+                if (self.reg_a ^ result as u8) & (data ^ result as u8) & 0x80 != 0 {
+                    self.status.insert(CpuFlags::OVERFLOW);
+                } else {
+                    self.status.remove(CpuFlags::OVERFLOW);
                 }
-                "*SHY" => {
-                    let mem_address =
-                        self.mem_read_u16(self.program_counter) + self.reg_x as u16;
-                    let data = self.reg_y & ((mem_address >> 8) as u8 + 1);
-                    self.mem_write(mem_address, data)
+        
+                self.reg_a = result as u8;
+                self.update_zero_n_negative_flag(self.reg_a);
+            }
+            Instruction::SLO => {
+                let data = self.asl(&opcode.mode);
+                self.reg_a |= data;
+                self.update_zero_n_negative_flag(self.reg_a);
+            }
+            Instruction::SRE => {
+                let data = self.lsr(&opcode.mode);
+                self.reg_a ^= data;
+                self.update_zero_n_negative_flag(self.reg_a);
+            }
+            Instruction::NOP => {
+                match opcode.mode {
+                    AddressingMode::NoneAddressing => { /* do nothing */ }
+                    _ => {
+                        let addr = self.get_operand_address(&opcode.mode);
+                        let _data = self.mem_read(addr);
+                        /* do nothing */
+                    }
                 }
-                _ => todo!(""),
             }
+            Instruction::LXA => {
+                self.lda(&opcode.mode);
+                self.tax();
+            }
+            Instruction::XAA => {
+                self.reg_a = self.reg_x;
+                self.update_zero_n_negative_flag(self.reg_a);
+                let addr = self.get_operand_address(&opcode.mode);
+                let data = self.mem_read(addr);
+                self.reg_a &= data;
+                self.update_zero_n_negative_flag(self.reg_a);
+            }
+            Instruction::LAS => {
+                let addr = self.get_operand_address(&opcode.mode);
+                let mut data = self.mem_read(addr);
+                data &= self.stack_pointer;
+                self.reg_a = data;
+                self.reg_x = data;
+                self.stack_pointer = data;
+                self.update_zero_n_negative_flag(data);
+            }
+            Instruction::TAS => {
+                let data = self.reg_a & self.reg_x;
+                self.stack_pointer = data;
+                let mem_address = self.get_store_address(&opcode.mode);
 
-            self.bus.tick(opcode.cycles);
-
-            if pc_state == self.program_counter {
-                self.program_counter += (opcode.len - 1) as u16;
+                let data = ((mem_address >> 8) as u8).wrapping_add(1) & self.stack_pointer;
+                self.mem_write(mem_address, data)
+            }
+            Instruction::AHX => {
+                let mem_address = self.get_store_address(&opcode.mode);
+                let data = self.reg_a & self.reg_x & (mem_address >> 8) as u8;
+                self.mem_write(mem_address, data)
+            }
+            Instruction::SHX => {
+                let mem_address = self.get_store_address(&opcode.mode);
+
+                // todo if cross page boundry {
+                //     mem_address &= (self.x as u16) << 8;
+                // }
+                let data = self.reg_x & ((mem_address >> 8) as u8).wrapping_add(1);
+                self.mem_write(mem_address, data)
+            }
+            Instruction::SHY => {
+                let mem_address = self.get_store_address(&opcode.mode);
+                let data = self.reg_y & ((mem_address >> 8) as u8).wrapping_add(1);
+                self.mem_write(mem_address, data)
             }
         }
+
+        // CLI, SEI and PLP change the I flag after the interrupt poll has
+        // already happened, so their effect is delayed by one instruction.
+        self.irq_inhibit = match opcode.instruction {
+            Instruction::CLI | Instruction::SEI | Instruction::PLP => interrupt_disable,
+            _ => self.status.contains(CpuFlags::INTERRUPT_DISABLE),
+        };
+
+        // control flow sets the PC itself, even when it lands back on the operand
+        let sets_pc = matches!(
+            opcode.instruction,
+            Instruction::BCC
+                | Instruction::BCS
+                | Instruction::BEQ
+                | Instruction::BMI
+                | Instruction::BNE
+                | Instruction::BPL
+                | Instruction::BVC
+                | Instruction::BVS
+                | Instruction::JMP
+                | Instruction::JSR
+                | Instruction::RTS
+                | Instruction::RTI
+                | Instruction::BRK
+        );
+        if pc_state == self.program_counter && !sets_pc {
+            self.program_counter = self.program_counter.wrapping_add((opcode.len - 1) as u16);
+        }
     }
 
     fn adc(&mut self, mode: &AddressingMode) {
-        let (addr, page_cross) = self.get_operand_address(mode);
+        let addr = self.get_operand_address(mode);
         let data = self.mem_read(addr);
 
         let carry = self.status.contains(CpuFlags::CARRY);
@@ -595,19 +708,12 @@ impl<'a> CPU<'a> {
 
         self.reg_a = result as u8;
         self.update_zero_n_negative_flag(self.reg_a);
-
-        if page_cross {
-            self.bus.tick(1);
-        }
     }
 
     fn and(&mut self, mode: &AddressingMode) {
-        let (addr, page_cross) = self.get_operand_address(mode);
+        let addr = self.get_operand_address(mode);
         self.reg_a = self.reg_a & self.mem_read(addr);
         self.update_zero_n_negative_flag(self.reg_a);
-        if page_cross {
-            self.bus.tick(1);
-        }
     }
 
     fn asl(&mut self, mode: &AddressingMode) -> u8 {
@@ -623,15 +729,10 @@ impl<'a> CPU<'a> {
                 self.reg_a
             }
             _ => {
-                let (addr, _) = self.get_operand_address(mode);
-                let data = self.mem_read(addr);
-                if data & 0b1000_0000 != 0 {
-                    self.status.insert(CpuFlags::CARRY);
-                } else {
-                    self.status.remove(CpuFlags::CARRY);
-                }
-                let result = data << 1;
-                self.mem_write(addr, result);
+                let result = self.read_modify_write(mode, |cpu, data| {
+                    cpu.status.set(CpuFlags::CARRY, data & 0b1000_0000 != 0);
+                    data << 1
+                });
                 self.update_zero_n_negative_flag(result);
                 result
             }
@@ -639,7 +740,7 @@ impl<'a> CPU<'a> {
     }
 
     fn bit(&mut self, mode: &AddressingMode) {
-        let (addr, _) = self.get_operand_address(mode);
+        let addr = self.get_operand_address(mode);
         let data = self.mem_read(addr);
         if self.reg_a & data == 0 {
             self.status.insert(CpuFlags::ZERO);
@@ -659,17 +760,19 @@ impl<'a> CPU<'a> {
     }
 
     fn branch(&mut self, condition: bool) {
+        let jump = self.mem_read(self.program_counter) as i8;
         if condition {
-            self.bus.tick(1);
-            let jump = self.mem_read(self.program_counter) as i8;
-            let jump_addr = self
-                .program_counter
-                .wrapping_add(1)
-                .wrapping_add(jump as u16);
-            if self.program_counter.wrapping_add(1) & 0xFF00 != jump_addr & 0xFF00 {
-                self.bus.tick(1);
+            // a taken branch reads the next opcode while adding the offset,
+            // and once more from the wrong page if the high byte has to be fixed
+            let next = self.program_counter.wrapping_add(1);
+            let jump_addr = next.wrapping_add(jump as u16);
+            self.mem_read(next);
+            if page_cross(next, jump_addr) {
+                self.mem_read(next & 0xFF00 | jump_addr & 0x00FF);
             }
             self.program_counter = jump_addr;
+        } else {
+            self.program_counter = self.program_counter.wrapping_add(1);
         }
     }
 
@@ -690,7 +793,7 @@ impl<'a> CPU<'a> {
     }
 
     fn compare(&mut self, mode: &AddressingMode, compare_with: u8) {
-        let (addr, page_cross) = self.get_operand_address(mode);
+        let addr = self.get_operand_address(mode);
         let data = self.mem_read(addr);
 
         if data <= compare_with {
@@ -700,10 +803,6 @@ impl<'a> CPU<'a> {
         }
 
         self.update_zero_n_negative_flag(compare_with.wrapping_sub(data));
-        
-        if page_cross {
-            self.bus.tick(1);
-        }
     }
 
     fn cmp(&mut self, mode: &AddressingMode) {
@@ -719,9 +818,7 @@ impl<'a> CPU<'a> {
     }
 
     fn dec(&mut self, mode: &AddressingMode) {
-        let (addr, _) = self.get_operand_address(mode);
-        let result = self.mem_read(addr).wrapping_sub(1);
-        self.mem_write(addr, result);
+        let result = self.read_modify_write(mode, |_, data| data.wrapping_sub(1));
         self.update_zero_n_negative_flag(result);
     }
 
@@ -736,20 +833,14 @@ impl<'a> CPU<'a> {
     }
 
     fn eor(&mut self, mode: &AddressingMode) {
-        let (addr, page_cross) = self.get_operand_address(mode);
+        let addr = self.get_operand_address(mode);
         let data = self.mem_read(addr);
         self.reg_a ^= data;
         self.update_zero_n_negative_flag(self.reg_a);
-
-        if page_cross {
-            self.bus.tick(1);
-        }
     }
 
     fn inc(&mut self, mode: &AddressingMode) -> u8 {
-        let (addr, _) = self.get_operand_address(mode);
-        let result = self.mem_read(addr).wrapping_add(1);
-        self.mem_write(addr, result);
+        let result = self.read_modify_write(mode, |_, data| data.wrapping_add(1));
         self.update_zero_n_negative_flag(result);
         result
     }
@@ -787,42 +878,34 @@ impl<'a> CPU<'a> {
         }
     }
 
+    // The high byte of the target is fetched last, after the return address is pushed.
     fn jsr(&mut self) {
-        self.stack_push_u16(self.program_counter + 2 - 1);
-        self.program_counter = self.mem_read_u16(self.program_counter);
+        let low = self.mem_read(self.program_counter) as u16;
+        self.stack_dummy_read();
+        self.stack_push_u16(self.program_counter.wrapping_add(2 - 1));
+        let high = self.mem_read(self.program_counter.wrapping_add(1)) as u16;
+        self.program_counter = high << 8 | low;
     }
 
     fn lda(&mut self, mode: &AddressingMode) {
-        let (addr, page_cross) = self.get_operand_address(mode);
+        let addr = self.get_operand_address(mode);
 
         self.reg_a = self.mem_read(addr);
         self.update_zero_n_negative_flag(self.reg_a);
-
-        if page_cross {
-            self.bus.tick(1);
-        }
     }
 
     fn ldx(&mut self, mode: &AddressingMode) {
-        let (addr, page_cross) = self.get_operand_address(mode);
+        let addr = self.get_operand_address(mode);
 
         self.reg_x = self.mem_read(addr);
         self.update_zero_n_negative_flag(self.reg_x);
-
-        if page_cross {
-            self.bus.tick(1);
-        }
     }
 
     fn ldy(&mut self, mode: &AddressingMode) {
-        let (addr, page_cross) = self.get_operand_address(mode);
+        let addr = self.get_operand_address(mode);
 
         self.reg_y = self.mem_read(addr);
         self.update_zero_n_negative_flag(self.reg_y);
-
-        if page_cross {
-            self.bus.tick(1);
-        }
     }
 
     fn lsr(&mut self, mode: &AddressingMode) -> u8 {
@@ -838,15 +921,10 @@ impl<'a> CPU<'a> {
                 self.reg_a
             }
             _ => {
-                let (addr, _) = self.get_operand_address(mode);
-                let data = self.mem_read(addr);
-                if data & 0b0000_0001 != 0 {
-                    self.status.insert(CpuFlags::CARRY);
-                } else {
-                    self.status.remove(CpuFlags::CARRY);
-                }
-                let result = data >> 1;
-                self.mem_write(addr, result);
+                let result = self.read_modify_write(mode, |cpu, data| {
+                    cpu.status.set(CpuFlags::CARRY, data & 0b0000_0001 != 0);
+                    data >> 1
+                });
                 self.update_zero_n_negative_flag(result);
                 result
             }
@@ -854,14 +932,10 @@ impl<'a> CPU<'a> {
     }
 
     fn ora(&mut self, mode: &AddressingMode) {
-        let (addr, page_cross) = self.get_operand_address(mode);
+        let addr = self.get_operand_address(mode);
         let data = self.mem_read(addr);
         self.reg_a |= data;
         self.update_zero_n_negative_flag(self.reg_a);
-
-        if page_cross {
-            self.bus.tick(1);
-        }
     }
 
     fn pha(&mut self) {
@@ -876,11 +950,13 @@ impl<'a> CPU<'a> {
     }
 
     fn pla(&mut self) {
+        self.stack_dummy_read();
         self.reg_a = self.stack_pop();
         self.update_zero_n_negative_flag(self.reg_a);
     }
 
     fn plp(&mut self) {
+        self.stack_dummy_read();
         self.status = CpuFlags::from_bits_truncate(self.stack_pop());
         self.status.remove(CpuFlags::BREAK);
         self.status.insert(CpuFlags::BREAK2);
@@ -903,18 +979,10 @@ impl<'a> CPU<'a> {
                 self.reg_a
             }
             _ => {
-                let (addr, _) = self.get_operand_address(mode);
-                let data = self.mem_read(addr);
-                if data & 0b1000_0000 != 0 {
-                    self.status.insert(CpuFlags::CARRY);
-                } else {
-                    self.status.remove(CpuFlags::CARRY);
-                }
-                let mut result = data << 1;
-                if carry {
-                    result |= 0b0000_0001;
-                }
-                self.mem_write(addr, result);
+                let result = self.read_modify_write(mode, |cpu, data| {
+                    cpu.status.set(CpuFlags::CARRY, data & 0b1000_0000 != 0);
+                    data << 1 | carry as u8
+                });
                 self.update_zero_n_negative_flag(result);
                 result
             }
@@ -938,18 +1006,10 @@ impl<'a> CPU<'a> {
                 self.reg_a
             }
             _ => {
-                let (addr, _) = self.get_operand_address(mode);
-                let data = self.mem_read(addr);
-                if data & 0b0000_0001 != 0 {
-                    self.status.insert(CpuFlags::CARRY);
-                } else {
-                    self.status.remove(CpuFlags::CARRY);
-                }
-                let mut result = data >> 1;
-                if carry {
-                    result |= 0b1000_0000;
-                }
-                self.mem_write(addr, result);
+                let result = self.read_modify_write(mode, |cpu, data| {
+                    cpu.status.set(CpuFlags::CARRY, data & 0b0000_0001 != 0);
+                    data >> 1 | (carry as u8) << 7
+                });
                 self.update_zero_n_negative_flag(result);
                 result
             }
@@ -957,6 +1017,7 @@ impl<'a> CPU<'a> {
     }
 
     fn rti(&mut self) {
+        self.stack_dummy_read();
         self.status = CpuFlags::from_bits_truncate(self.stack_pop());
         self.status.remove(CpuFlags::BREAK);
         self.status.insert(CpuFlags::BREAK2);
@@ -964,11 +1025,15 @@ impl<'a> CPU<'a> {
     }
 
     fn rts(&mut self) {
-        self.program_counter = self.stack_pop_u16() + 1;
+        self.stack_dummy_read();
+        let addr = self.stack_pop_u16();
+        // the pulled address is read once more while it gets incremented
+        self.mem_read(addr);
+        self.program_counter = addr.wrapping_add(1);
     }
 
     fn sbc(&mut self, mode: &AddressingMode) {
-        let (addr, page_cross) = self.get_operand_address(mode);
+        let addr = self.get_operand_address(mode);
         let base_data = self.mem_read(addr);
         let data = (base_data as i8).wrapping_neg().wrapping_sub(1) as u8;
 
@@ -990,10 +1055,6 @@ impl<'a> CPU<'a> {
 
         self.reg_a = result as u8;
         self.update_zero_n_negative_flag(self.reg_a);
-
-        if page_cross {
-            self.bus.tick(1);
-        }
     }
 
     fn sec(&mut self) {
@@ -1009,17 +1070,17 @@ impl<'a> CPU<'a> {
     }
 
     fn sta(&mut self, mode: &AddressingMode) {
-        let (addr, _) = self.get_operand_address(mode);
+        let addr = self.get_store_address(mode);
         self.mem_write(addr, self.reg_a);
     }
 
     fn stx(&mut self, mode: &AddressingMode) {
-        let (addr, _) = self.get_operand_address(mode);
+        let addr = self.get_store_address(mode);
         self.mem_write(addr, self.reg_x);
     }
 
     fn sty(&mut self, mode: &AddressingMode) {
-        let (addr, _) = self.get_operand_address(mode);
+        let addr = self.get_store_address(mode);
         self.mem_write(addr, self.reg_y);
     }
 
@@ -1067,55 +1128,113 @@ impl<'a> CPU<'a> {
         }
     }
 
-    fn get_operand_address(&mut self, mode: &AddressingMode) -> (u16, bool) {
-        match mode {
-            AddressingMode::Immediate => (self.program_counter, false),
-            _ => self.get_absolute_address(mode, self.program_counter),
-        }
+    fn get_operand_address(&mut self, mode: &AddressingMode) -> u16 {
+        self.operand_address(mode, false)
+    }
+
+    // Stores and read-modify-write instructions always spend the cycle that
+    // fixes up the high byte of an indexed address, reads only on a page cross.
+    fn get_store_address(&mut self, mode: &AddressingMode) -> u16 {
+        self.operand_address(mode, true)
     }
 
-    pub fn get_absolute_address(&mut self, mode: &AddressingMode, addr: u16) -> (u16, bool) {
+    fn operand_address(&mut self, mode: &AddressingMode, store: bool) -> u16 {
+        let pc = self.program_counter;
         match mode {
-            AddressingMode::ZeroPage => (self.mem_read(addr) as u16, false),
+            AddressingMode::Immediate => pc,
+            AddressingMode::ZeroPage => self.mem_read(pc) as u16,
             AddressingMode::ZeroPage_X => {
-                let pos = self.mem_read(addr);
-                (pos.wrapping_add(self.reg_x) as u16, false)
+                let pos = self.mem_read(pc);
+                self.mem_read(pos as u16);
+                pos.wrapping_add(self.reg_x) as u16
             }
             AddressingMode::ZeroPage_Y => {
-                let pos = self.mem_read(addr);
-                (pos.wrapping_add(self.reg_y) as u16, false)
+                let pos = self.mem_read(pc);
+                self.mem_read(pos as u16);
+                pos.wrapping_add(self.reg_y) as u16
             }
-            AddressingMode::Absolute => (self.mem_read_u16(addr), false),
+            AddressingMode::Absolute => self.mem_read_u16(pc),
             AddressingMode::Absolute_X => {
-                let pos = self.mem_read_u16(addr);
-                let addr = pos.wrapping_add(self.reg_x as u16);
-                (addr, page_cross(pos, addr))
+                let pos = self.mem_read_u16(pc);
+                self.indexed(pos, self.reg_x, store)
             }
             AddressingMode::Absolute_Y => {
-                let pos = self.mem_read_u16(addr);
-                let addr = pos.wrapping_add(self.reg_y as u16);
-                (addr, page_cross(pos, addr))
-            }
-            AddressingMode::Indirect => {
-                panic!(
-                    "Adressing Mode {:?} is not supported in this function",
-                    mode
-                );
+                let pos = self.mem_read_u16(pc);
+                self.indexed(pos, self.reg_y, store)
             }
             AddressingMode::Indirect_X => {
-                let base = self.mem_read(addr);
+                let base = self.mem_read(pc);
+                self.mem_read(base as u16);
                 let ptr = base.wrapping_add(self.reg_x);
                 let low = self.mem_read(ptr as u16);
                 let high = self.mem_read(ptr.wrapping_add(1) as u16);
-                ((high as u16) << 8 | (low as u16), false)
+                (high as u16) << 8 | (low as u16)
             }
             AddressingMode::Indirect_Y => {
-                let base = self.mem_read(addr);
+                let base = self.mem_read(pc);
                 let low = self.mem_read(base as u16);
                 let high = self.mem_read(base.wrapping_add(1) as u16);
                 let deref_base = (high as u16) << 8 | (low as u16);
-                let deref = deref_base.wrapping_add(self.reg_y as u16);
-                (deref, page_cross(deref, deref_base))
+                self.indexed(deref_base, self.reg_y, store)
+            }
+            _ => {
+                panic!("Addressing Mode {:?} is not supported", mode);
+            }
+        }
+    }
+
+    // The index is added to the low byte first, and the CPU reads from that
+    // address before the carry reaches the high byte.
+    fn indexed(&mut self, base: u16, index: u8, store: bool) -> u16 {
+        let addr = base.wrapping_add(index as u16);
+        if store || page_cross(base, addr) {
+            self.mem_read(base & 0xFF00 | addr & 0x00FF);
+        }
+        addr
+    }
+
+    // Reads the operand, writes it back unchanged while `modify` works on it,
+    // then writes the result.
+    fn read_modify_write<F>(&mut self, mode: &AddressingMode, modify: F) -> u8
+    where
+        F: FnOnce(&mut Self, u8) -> u8,
+    {
+        let addr = self.get_store_address(mode);
+        let data = self.mem_read(addr);
+        self.mem_write(addr, data);
+        let result = modify(self, data);
+        self.mem_write(addr, result);
+        result
+    }
+
+    // Effective address of an operand at `addr` as the tracer shows it.
+    // Looks at memory through the bus directly, so no cycles pass.
+    pub fn get_absolute_address(&mut self, mode: &AddressingMode, addr: u16) -> u16 {
+        match mode {
+            AddressingMode::ZeroPage => self.bus.mem_read(addr) as u16,
+            AddressingMode::ZeroPage_X => {
+                let pos = self.bus.mem_read(addr);
+                pos.wrapping_add(self.reg_x) as u16
+            }
+            AddressingMode::ZeroPage_Y => {
+                let pos = self.bus.mem_read(addr);
+                pos.wrapping_add(self.reg_y) as u16
+            }
+            AddressingMode::Absolute => self.bus.mem_read_u16(addr),
+            AddressingMode::Absolute_X => self.bus.mem_read_u16(addr).wrapping_add(self.reg_x as u16),
+            AddressingMode::Absolute_Y => self.bus.mem_read_u16(addr).wrapping_add(self.reg_y as u16),
+            AddressingMode::Indirect_X => {
+                let base = self.bus.mem_read(addr);
+                let ptr = base.wrapping_add(self.reg_x);
+                let low = self.bus.mem_read(ptr as u16);
+                let high = self.bus.mem_read(ptr.wrapping_add(1) as u16);
+                (high as u16) << 8 | (low as u16)
+            }
+            AddressingMode::Indirect_Y => {
+                let base = self.bus.mem_read(addr);
+                let low = self.bus.mem_read(base as u16);
+                let high = self.bus.mem_read(base.wrapping_add(1) as u16);
+                ((high as u16) << 8 | (low as u16)).wrapping_add(self.reg_y as u16)
             }
             _ => {
                 panic!("Addressing Mode {:?} is not supported", mode);
@@ -1124,11 +1243,205 @@ impl<'a> CPU<'a> {
     }
 }
 
+// Loading a program means writing the reset vector, which only RAM can take.
+impl CPU<FlatBus> {
+    pub fn load_and_run(&mut self, program: Vec<u8>) {
+        self.load(program);
+        self.power_on();
+        self.run();
+    }
+
+    pub fn load(&mut self, program: Vec<u8>) {
+        for i in 0..(program.len() as u16) {
+            self.bus.mem_write(0x0600 + i, program[i as usize])
+        }
+        self.bus.mem_write_u16(0xFFFC, 0x0600);
+    }
+}
+
 fn page_cross(addr1: u16, addr2 : u16) -> bool {
     addr1 & 0xFF00 != addr2 & 0xFF00
 }
 
 #[cfg(test)]
 mod tests {
-    // use super::*;
+    use super::*;
+
+    fn run_program(program: Vec<u8>, instructions: usize) -> CPU<FlatBus> {
+        let mut cpu = CPU::new(FlatBus::new());
+        cpu.load(program);
+        cpu.power_on();
+        for _ in 0..instructions {
+            cpu.step();
+        }
+        cpu
+    }
+
+    // FlatBus with an IRQ line the test drives.
+    struct IrqBus {
+        bus: FlatBus,
+        irq: bool,
+    }
+
+    impl Mem for IrqBus {
+        fn mem_read(&mut self, addr: u16) -> u8 {
+            self.bus.mem_read(addr)
+        }
+
+        fn mem_write(&mut self, addr: u16, data: u8) {
+            self.bus.mem_write(addr, data)
+        }
+    }
+
+    impl CpuBus for IrqBus {
+        fn tick(&mut self, cycles: u8) {
+            self.bus.tick(cycles)
+        }
+
+        fn poll_nmi_status(&mut self) -> Option<u8> {
+            None
+        }
+
+        fn poll_irq_status(&self) -> bool {
+            self.irq
+        }
+    }
+
+    // The program at $0600 and an IRQ handler at $0700 that only does RTI.
+    fn irq_cpu(program: Vec<u8>) -> CPU<IrqBus> {
+        let mut bus = FlatBus::new();
+        for (i, data) in program.iter().enumerate() {
+            bus.mem_write(0x0600 + i as u16, *data);
+        }
+        bus.mem_write(0x0700, 0x40);
+        bus.mem_write_u16(0xFFFC, 0x0600);
+        bus.mem_write_u16(0xFFFE, 0x0700);
+        let mut cpu = CPU::new(IrqBus { bus, irq: false });
+        cpu.power_on();
+        cpu
+    }
+
+    #[test]
+    fn test_0xa9_lda_immediate_load_data() {
+        let cpu = run_program(vec![0xa9, 0x05], 1);
+        assert_eq!(cpu.reg_a, 0x05);
+        assert!(!cpu.status.contains(CpuFlags::ZERO));
+        assert!(!cpu.status.contains(CpuFlags::NEGATIV));
+    }
+
+    #[test]
+    fn test_5_ops_working_together() {
+        let cpu = run_program(vec![0xa9, 0xc0, 0xaa, 0xe8, 0x00], 3);
+        assert_eq!(cpu.reg_x, 0xc1);
+    }
+
+    #[test]
+    fn test_inx_overflow() {
+        let cpu = run_program(vec![0xa2, 0xff, 0xe8, 0xe8], 3);
+        assert_eq!(cpu.reg_x, 1);
+    }
+
+    #[test]
+    fn test_sta_writes_to_ram() {
+        let mut cpu = run_program(vec![0xa9, 0x55, 0x85, 0x10], 2);
+        assert_eq!(cpu.bus.mem_read(0x10), 0x55);
+        assert_eq!(cpu.bus.cycles(), 7 + 2 + 3);
+    }
+
+    #[test]
+    fn test_page_cross_penalty_on_reads_only() {
+        // LDX #$01; LDA $10FF,X; STA $10FF,X; INC $10FF,X
+        let cpu = run_program(vec![0xa2, 0x01, 0xbd, 0xff, 0x10, 0x9d, 0xff, 0x10, 0xfe, 0xff, 0x10], 4);
+        assert_eq!(cpu.bus.cycles(), 7 + 2 + 5 + 5 + 7);
+    }
+
+    #[test]
+    fn test_branch_cycles() {
+        // BNE not taken, BEQ taken within the page, then BEQ taken across it at $06FC
+        let mut program = vec![0xd0, 0x10, 0xf0, 0x00];
+        program.resize(0xfc, 0xea);
+        program.extend([0xf0, 0x02]);
+        let mut cpu = CPU::new(FlatBus::new());
+        cpu.load(program);
+        cpu.power_on();
+        cpu.status.insert(CpuFlags::ZERO);
+        cpu.step();
+        cpu.step();
+        assert_eq!(cpu.bus.cycles(), 7 + 2 + 3);
+        cpu.program_counter = 0x06fc;
+        cpu.step();
+        assert_eq!(cpu.program_counter, 0x0700);
+        assert_eq!(cpu.bus.cycles(), 7 + 2 + 3 + 4);
+    }
+
+    #[test]
+    fn test_branch_back_onto_its_operand() {
+        // BNE $FF jumps to the offset byte itself, which runs as the next opcode
+        let cpu = run_program(vec![0xd0, 0xff], 1);
+        assert_eq!(cpu.program_counter, 0x0601);
+    }
+
+    #[test]
+    fn test_jam_halts_until_reset() {
+        let mut cpu = run_program(vec![0xa9, 0x01, 0x02, 0xe8], 4);
+        assert_eq!(cpu.halted(), Some(CpuHalted { pc: 0x0602, opcode: 0x02 }));
+        assert_eq!(cpu.reg_x, 0);
+
+        let cycles = cpu.bus.cycles();
+        cpu.step();
+        assert_eq!(cpu.bus.cycles(), cycles + 1);
+
+        cpu.soft_reset();
+        assert_eq!(cpu.halted(), None);
+    }
+
+    #[test]
+    fn test_soft_reset_keeps_registers() {
+        let mut cpu = run_program(vec![0xa9, 0x42, 0xa2, 0x07, 0x58], 3);
+        cpu.soft_reset();
+        assert_eq!(cpu.reg_a, 0x42);
+        assert_eq!(cpu.reg_x, 0x07);
+        assert_eq!(cpu.stack_pointer, 0xfa);
+        assert_eq!(cpu.program_counter, 0x0600);
+        assert!(cpu.status.contains(CpuFlags::INTERRUPT_DISABLE));
+    }
+
+    #[test]
+    fn test_irq_waits_one_instruction_after_cli() {
+        // CLI; NOP; NOP
+        let mut cpu = irq_cpu(vec![0x58, 0xea, 0xea]);
+        cpu.bus.irq = true;
+        cpu.step();
+        // the poll at the end of CLI still saw I set, so the NOP runs first
+        cpu.step();
+        assert_eq!(cpu.program_counter, 0x0602);
+        assert_eq!(cpu.stack_pointer, 0xfd);
+
+        cpu.step();
+        assert_eq!(cpu.bus.mem_read(0x01fd), 0x06);
+        assert_eq!(cpu.bus.mem_read(0x01fc), 0x02);
+        assert_eq!(cpu.program_counter, 0x0602);
+    }
+
+    #[test]
+    fn test_one_irq_gets_through_after_sei() {
+        // SEI; NOP; NOP
+        let mut cpu = irq_cpu(vec![0x78, 0xea, 0xea]);
+        cpu.status.remove(CpuFlags::INTERRUPT_DISABLE);
+        cpu.irq_inhibit = false;
+        cpu.step();
+        cpu.bus.irq = true;
+
+        // the poll at the end of SEI still saw I clear
+        cpu.step();
+        assert_eq!(cpu.bus.mem_read(0x01fc), 0x01);
+        assert_ne!(cpu.bus.mem_read(0x01fb) & CpuFlags::INTERRUPT_DISABLE.bits(), 0);
+        assert_eq!(cpu.program_counter, 0x0601);
+
+        // RTI brought back the I flag SEI set, the line is ignored from now on
+        cpu.step();
+        cpu.step();
+        assert_eq!(cpu.program_counter, 0x0603);
+        assert_eq!(cpu.stack_pointer, 0xfd);
+    }
 }
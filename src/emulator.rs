@@ -0,0 +1,142 @@
+use crate::apu::{sounds::SoundManager, NesAPU};
+use crate::bus::{Bus, RamInit};
+use crate::cartridge::Rom;
+use crate::cpu::{CPU, CPU_FREQ};
+use crate::joypad::{Joypad, JoypadButton};
+use crate::ppu::NesPPU;
+
+// The emulator core without any window or audio device attached.
+// The caller decides when to run it, then reads back the picture and the sound.
+pub struct Emulator {
+    cpu: CPU<Bus<'static>>,
+    sound: SoundManager,
+    sample_rate: f32,
+    sample_clock: f32,
+    samples: Vec<f32>,
+}
+
+impl Emulator {
+    pub fn new(rom: Rom, sample_rate: f32) -> Self {
+        let (sound, txs) = SoundManager::new(sample_rate);
+        let bus = Bus::new(rom, |_: &NesPPU, _: &mut Joypad| {}, NesAPU::new(txs));
+        let mut cpu = CPU::new(bus);
+        cpu.power_on();
+        Emulator {
+            cpu,
+            sound,
+            sample_rate,
+            sample_clock: 0.0,
+            samples: Vec::new(),
+        }
+    }
+
+    // Takes effect at the next `power_on`; `new` has already powered on with zeroed RAM.
+    pub fn set_ram_init(&mut self, ram_init: RamInit) {
+        self.cpu.bus.set_ram_init(ram_init);
+    }
+
+    pub fn power_on(&mut self) {
+        self.cpu.power_on();
+    }
+
+    pub fn soft_reset(&mut self) {
+        self.cpu.soft_reset();
+    }
+
+    pub fn cpu(&self) -> &CPU<Bus<'static>> {
+        &self.cpu
+    }
+
+    pub fn cpu_mut(&mut self) -> &mut CPU<Bus<'static>> {
+        &mut self.cpu
+    }
+
+    pub fn step_instruction(&mut self) {
+        let cycles_before = self.cpu.bus.cycles();
+        self.cpu.step();
+        let cycles = self.cpu.bus.cycles() - cycles_before;
+
+        // generate audio at the output rate as CPU time goes by
+        self.sample_clock += cycles as f32 * self.sample_rate / CPU_FREQ;
+        while self.sample_clock >= 1.0 {
+            self.samples.push(self.sound.get_sound());
+            self.sample_clock -= 1.0;
+        }
+    }
+
+    // Runs until the PPU reaches the next vblank, when the picture is complete.
+    pub fn run_frame(&mut self) {
+        let frame = self.cpu.bus.frame_count();
+        while self.cpu.bus.frame_count() == frame {
+            self.step_instruction();
+        }
+    }
+
+    // 256x240 RGB24 pixels of the last frame finished by `run_frame`.
+    pub fn framebuffer(&self) -> &[u8] {
+        &self.cpu.bus.ppu().frame.data
+    }
+
+    pub fn set_buttons(&mut self, buttons: JoypadButton) {
+        self.cpu.bus.joypad1().set_buttons(buttons);
+    }
+
+    // Samples produced since the last call, in the range the SDL frontend feeds to cpal.
+    pub fn audio_samples(&mut self) -> Vec<f32> {
+        std::mem::take(&mut self.samples)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartridge::test::ines_image;
+    use crate::cpu::Mem;
+
+    // NROM image running: strobe the controller, store its first bit at $00,
+    // count loops at $01, repeat.
+    fn emulator() -> Emulator {
+        let program = [
+            0xa9, 0x01, 0x8d, 0x16, 0x40, // LDA #1; STA $4016
+            0xa9, 0x00, 0x8d, 0x16, 0x40, // LDA #0; STA $4016
+            0xad, 0x16, 0x40, 0x85, 0x00, // LDA $4016; STA $00
+            0xe6, 0x01, // INC $01
+            0x4c, 0x00, 0x80, // JMP $8000
+        ];
+        let mut image = ines_image(0, 1, 1);
+        image[16..16 + program.len()].copy_from_slice(&program);
+        image[16 + 0x3ffc] = 0x00;
+        image[16 + 0x3ffd] = 0x80;
+        Emulator::new(Rom::new(&image).unwrap(), 44100.0)
+    }
+
+    #[test]
+    fn test_step_instruction_runs_one_instruction() {
+        let mut emulator = emulator();
+        assert_eq!(emulator.cpu().program_counter, 0x8000);
+        emulator.step_instruction();
+        assert_eq!(emulator.cpu().program_counter, 0x8002);
+        assert_eq!(emulator.cpu().reg_a, 1);
+    }
+
+    #[test]
+    fn test_run_frame_with_buttons_and_audio() {
+        let mut emulator = emulator();
+        emulator.set_buttons(JoypadButton::BUTTON_A);
+        emulator.run_frame();
+        emulator.audio_samples();
+        assert_eq!(emulator.cpu().bus.frame_count(), 1);
+        assert_eq!(emulator.cpu_mut().bus.mem_read(0x00) & 1, 1);
+
+        let loops = emulator.cpu_mut().bus.mem_read(0x01);
+        emulator.run_frame();
+        assert_eq!(emulator.cpu().bus.frame_count(), 2);
+        assert_ne!(emulator.cpu_mut().bus.mem_read(0x01), loops);
+
+        // a frame is about 29781 CPU cycles, 734 samples at 44.1 kHz
+        let samples = emulator.audio_samples();
+        assert!((730..=738).contains(&samples.len()), "{} samples", samples.len());
+        assert!(emulator.audio_samples().is_empty());
+        assert_eq!(emulator.framebuffer().len(), 256 * 240 * 3);
+    }
+}
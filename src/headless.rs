@@ -0,0 +1,171 @@
+use std::{env, fs, path::PathBuf, process};
+
+use getopts::Options;
+use nes_rs::bus::RamInit;
+use nes_rs::cartridge::Rom;
+use nes_rs::cpu::Mem;
+use nes_rs::emulator::Emulator;
+use nes_rs::joypad::JoypadButton;
+use nes_rs::savestate;
+
+const SAMPLE_RATE: f32 = 44100.0;
+const DEFAULT_FRAMES: usize = 600;
+
+// Runs a ROM without a window or audio device, for CI and batch jobs.
+//
+// Input scripts hold one "<frame> <buttons>" line per change, where buttons are
+// joined with '+' (A, B, SELECT, START, UP, DOWN, LEFT, RIGHT) or '-' for none.
+// Each line takes effect at the start of its frame and lasts until the next one.
+//
+// Exits with 2 if the --until condition was never met and 3 if the CPU halted.
+fn main() {
+    let mut opts = Options::new();
+    opts.optopt("f", "frames", "Number of frames to run, or the limit with --until (default 600).", "N");
+    opts.optopt("u", "until", "Stop once the byte at ADDR equals VALUE, both hex.", "ADDR=VALUE");
+    opts.optopt("i", "input", "Controller input script.", "FILE");
+    opts.optopt("", "ram-init", "Power-on RAM contents: zeros, ff, pattern or random[:SEED] (default zeros).", "INIT");
+    opts.optopt("s", "screenshot", "Write the final frame as a PPM image.", "FILE");
+    opts.optflag("", "hash", "Print a hash of the final frame.");
+    opts.optopt("r", "dump-ram", "Write the 2K of CPU RAM.", "FILE");
+    opts.optflag("h", "help", "Print this help.");
+
+    let args: Vec<String> = env::args().collect();
+    let match_opts = match opts.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(err) => fail(&err.to_string()),
+    };
+    if match_opts.opt_present("h") || match_opts.free.is_empty() {
+        print!("{}", opts.usage("Usage: nes-headless [options] ROM"));
+        return;
+    }
+
+    let frames: usize = match_opts
+        .opt_get_default("f", DEFAULT_FRAMES)
+        .unwrap_or_else(|err| fail(&format!("Invalid frame count: {}", err)));
+    let ram_init: RamInit = match_opts
+        .opt_get_default("ram-init", RamInit::Zeros)
+        .unwrap_or_else(|err| fail(&err));
+    let until = match_opts.opt_str("u").map(|arg| parse_until(&arg).unwrap_or_else(|err| fail(&err)));
+    let script = match match_opts.opt_str("i") {
+        Some(path) => {
+            let text = fs::read_to_string(&path).unwrap_or_else(|err| fail(&format!("{}: {}", path, err)));
+            parse_script(&text).unwrap_or_else(|err| fail(&format!("{}: {}", path, err)))
+        }
+        None => Vec::new(),
+    };
+
+    let rom_path = PathBuf::from(&match_opts.free[0]);
+    let bytes = fs::read(&rom_path).unwrap_or_else(|err| fail(&format!("{}: {}", rom_path.display(), err)));
+    let rom = Rom::new(&bytes).unwrap_or_else(|err| fail(&err));
+    let mut emulator = Emulator::new(rom, SAMPLE_RATE);
+    if ram_init != RamInit::Zeros {
+        if let RamInit::Random(seed) = ram_init {
+            println!("RAM seed {}", seed);
+        }
+        emulator.set_ram_init(ram_init);
+        emulator.power_on();
+    }
+
+    let mut script = script.into_iter().peekable();
+    let mut reached = until.is_none();
+    let mut frame = 0;
+    while frame < frames && emulator.cpu().halted().is_none() {
+        while let Some((_, buttons)) = script.next_if(|(at, _)| *at <= frame) {
+            emulator.set_buttons(buttons);
+        }
+        emulator.run_frame();
+        // nobody is listening, don't let the samples pile up
+        emulator.audio_samples();
+        frame += 1;
+
+        if let Some((addr, value)) = until {
+            if emulator.cpu_mut().bus.mem_read(addr) == value {
+                reached = true;
+                break;
+            }
+        }
+    }
+    println!("Ran {} frames", frame);
+    if let Some(halted) = emulator.cpu().halted() {
+        eprintln!("CPU halted on opcode ${:02X} at ${:04X}", halted.opcode, halted.pc);
+    }
+
+    if let Some(path) = match_opts.opt_str("s") {
+        let mut image = b"P6\n256 240\n255\n".to_vec();
+        image.extend_from_slice(emulator.framebuffer());
+        fs::write(&path, image).unwrap_or_else(|err| fail(&format!("{}: {}", path, err)));
+    }
+    if match_opts.opt_present("hash") {
+        // same FNV-1a that ties save states to their ROM
+        println!("Frame hash {:016x}", savestate::rom_hash(emulator.framebuffer()));
+    }
+    if let Some(path) = match_opts.opt_str("r") {
+        let ram: Vec<u8> = (0..0x800).map(|addr| emulator.cpu_mut().bus.mem_read(addr)).collect();
+        fs::write(&path, ram).unwrap_or_else(|err| fail(&format!("{}: {}", path, err)));
+    }
+
+    if !reached {
+        eprintln!("Condition not met after {} frames", frame);
+        process::exit(2);
+    }
+    if emulator.cpu().halted().is_some() {
+        process::exit(3);
+    }
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(1)
+}
+
+// Only RAM and cartridge space can be read without side effects on the PPU or APU.
+fn parse_until(arg: &str) -> Result<(u16, u8), String> {
+    let (addr, value) = arg
+        .split_once('=')
+        .ok_or_else(|| format!("Expected ADDR=VALUE, got {}", arg))?;
+    let addr = u16::from_str_radix(addr.trim_start_matches('$'), 16)
+        .map_err(|err| format!("Invalid address {}: {}", addr, err))?;
+    let value = u8::from_str_radix(value.trim_start_matches('$'), 16)
+        .map_err(|err| format!("Invalid value {}: {}", value, err))?;
+    if (0x2000..0x4020).contains(&addr) {
+        return Err(format!("Address {:04x} is a hardware register", addr));
+    }
+    Ok((addr, value))
+}
+
+fn parse_script(text: &str) -> Result<Vec<(usize, JoypadButton)>, String> {
+    let mut script = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (frame, buttons) = line
+            .split_once(char::is_whitespace)
+            .ok_or_else(|| format!("line {}: expected <frame> <buttons>", number + 1))?;
+        let frame = frame
+            .parse()
+            .map_err(|err| format!("line {}: invalid frame {}: {}", number + 1, frame, err))?;
+
+        let mut pressed = JoypadButton::empty();
+        let buttons = buttons.trim();
+        if buttons != "-" {
+            for name in buttons.split('+') {
+                pressed |= match name.trim().to_ascii_uppercase().as_str() {
+                    "A" => JoypadButton::BUTTON_A,
+                    "B" => JoypadButton::BUTTON_B,
+                    "SELECT" => JoypadButton::SELECT,
+                    "START" => JoypadButton::START,
+                    "UP" => JoypadButton::UP,
+                    "DOWN" => JoypadButton::DOWN,
+                    "LEFT" => JoypadButton::LEFT,
+                    "RIGHT" => JoypadButton::RIGHT,
+                    other => return Err(format!("line {}: unknown button {}", number + 1, other)),
+                };
+            }
+        }
+        script.push((frame, pressed));
+    }
+    script.sort_by_key(|(frame, _)| *frame);
+    Ok(script)
+}
@@ -1,6 +1,17 @@
+bitflags! {
+    // Devices that can pull the shared /IRQ line low. The line stays asserted
+    // while any of them is set.
+    pub struct IrqSource: u8 {
+        const FRAME_COUNTER = 0b0000_0001;
+        const DMC           = 0b0000_0010;
+        const MAPPER        = 0b0000_0100;
+    }
+}
+
 #[derive(PartialEq, Eq)]
 pub enum InterruptType {
     NMI,
+    IRQ,
     BRK,
 }
 
@@ -9,19 +20,28 @@ pub(super) struct Interrupt {
     pub(super) itype: InterruptType,
     pub(super) vector_addr: u16,
     pub(super) b_flag_mask: u8,
-    pub(super) cpu_cycles: u8,
+    // reads of the current PC before the pushes
+    pub(super) dummy_reads: u8,
 }
 
 pub(super) const NMI: Interrupt = Interrupt {
     itype: InterruptType::NMI,
     vector_addr: 0xFFFA,
     b_flag_mask: 0b00100000,
-    cpu_cycles: 2,
+    dummy_reads: 2,
+};
+
+pub(super) const IRQ: Interrupt = Interrupt {
+    itype: InterruptType::IRQ,
+    vector_addr: 0xFFFE,
+    b_flag_mask: 0b00100000,
+    dummy_reads: 2,
 };
 
 pub(super) const BRK: Interrupt = Interrupt {
     itype: InterruptType::BRK,
     vector_addr: 0xFFFE,
     b_flag_mask: 0b0011_0000,
-    cpu_cycles: 1,
+    // taken by the opcode fetch and the padding byte
+    dummy_reads: 0,
 };
\ No newline at end of file
@@ -1,3 +1,5 @@
+use crate::savestate::{Snapshot, StateReader, StateWriter};
+
 bitflags! {
     // https://wiki.nesdev.com/w/index.php/Controller_reading_code
     pub struct JoypadButton: u8 {
@@ -45,7 +47,33 @@ impl Joypad {
         response
     }
 
+    // Clears the strobe and the shift position, the buttons are whatever is held down.
+    pub fn power_on(&mut self) {
+        self.strobe = false;
+        self.button_index = 0;
+    }
+
     pub fn set_button_pressed_status(&mut self, button: JoypadButton, pressed: bool) {
         self.button_status.set(button, pressed);
     }
+
+    // Replaces the state of all eight buttons at once.
+    pub fn set_buttons(&mut self, buttons: JoypadButton) {
+        self.button_status = buttons;
+    }
+}
+
+impl Snapshot for Joypad {
+    fn save_state(&self, w: &mut StateWriter) {
+        w.write_bool(self.strobe);
+        w.write_u8(self.button_index);
+        w.write_u8(self.button_status.bits);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), String> {
+        self.strobe = r.read_bool()?;
+        self.button_index = r.read_u8()?;
+        self.button_status = JoypadButton::from_bits_truncate(r.read_u8()?);
+        Ok(())
+    }
 }
@@ -0,0 +1,18 @@
+pub mod apu;
+pub mod battery;
+pub mod bus;
+pub mod cartridge;
+pub mod cpu;
+pub mod emulator;
+pub mod interrupt;
+pub mod joypad;
+pub mod mapper;
+pub mod opcodes;
+pub mod ppu;
+pub mod renderer;
+pub mod rewind;
+pub mod savestate;
+pub mod trace;
+
+#[macro_use]
+extern crate bitflags;
@@ -1,36 +1,47 @@
-pub mod apu;
-pub mod bus;
-pub mod cartridge;
-pub mod cpu;
-pub mod interrupt;
-pub mod joypad;
-pub mod opcodes;
-pub mod ppu;
-pub mod renderer;
-pub mod trace;
-
-use std::{collections::HashMap, env};
-
-use apu::{
-    sounds::{SoundManager, Transmitters},
-    NesAPU,
-};
-use bus::Bus;
-use cartridge::Rom;
+use std::{cell::Cell, collections::HashMap, env, path::PathBuf, rc::Rc};
+
 use cpal::{
     traits::{DeviceTrait, HostTrait, StreamTrait},
     FromSample, SizedSample, Stream,
 };
-use cpu::CPU;
 use getopts::Options;
-use joypad::Joypad;
-use ppu::NesPPU;
-use renderer::frame::Frame;
-// use trace::trace;
+use nes_rs::apu::{
+    sounds::{SoundManager, Transmitters},
+    NesAPU,
+};
+use nes_rs::battery::BatterySave;
+use nes_rs::bus::{Bus, RamInit};
+use nes_rs::cartridge::Rom;
+use nes_rs::cpu::{CpuHalted, CPU};
+use nes_rs::joypad::{self, Joypad};
+use nes_rs::ppu::NesPPU;
+use nes_rs::rewind::Rewind;
+use nes_rs::{savestate, trace};
 use sdl2::{event::Event, keyboard::Keycode, pixels::PixelFormatEnum};
 
-#[macro_use]
-extern crate bitflags;
+// Save state and reset requests raised from the SDL event loop and carried out by the CPU loop.
+#[derive(Clone, Copy)]
+enum StateCommand {
+    Save(u8),
+    Load(u8),
+    Reset,
+}
+
+fn slot_for_key(keycode: Keycode) -> Option<u8> {
+    match keycode {
+        Keycode::Num0 => Some(0),
+        Keycode::Num1 => Some(1),
+        Keycode::Num2 => Some(2),
+        Keycode::Num3 => Some(3),
+        Keycode::Num4 => Some(4),
+        Keycode::Num5 => Some(5),
+        Keycode::Num6 => Some(6),
+        Keycode::Num7 => Some(7),
+        Keycode::Num8 => Some(8),
+        Keycode::Num9 => Some(9),
+        _ => None,
+    }
+}
 
 fn main() {
     // init sdl2
@@ -82,14 +93,27 @@ fn main() {
 
     let mut opts = Options::new();
     opts.optflag("t", "trace", "Turn on operation tracing.");
+    opts.optopt("", "rewind-mb", "Memory budget for rewind history (default 100, 0 disables).", "MB");
+    opts.optopt("", "rewind-interval", "Frames between rewind snapshots (default 2).", "FRAMES");
+    opts.optopt("", "ram-init", "Power-on RAM contents: zeros, ff, pattern or random[:SEED] (default zeros).", "INIT");
 
     let args: Vec<String> = env::args().collect();
     let match_opts = opts.parse(&args[1..]).unwrap();
 
-    let bytes = std::fs::read(match_opts.free[0].clone()).unwrap();
+    let rewind_mb: usize = match_opts.opt_get_default("rewind-mb", 100).unwrap();
+    let rewind_interval: u32 = match_opts.opt_get_default("rewind-interval", 2).unwrap();
+    let ram_init: RamInit = match_opts.opt_get_default("ram-init", RamInit::Zeros).unwrap();
+    if let RamInit::Random(seed) = ram_init {
+        println!("RAM seed {}", seed);
+    }
+
+    let rom_path = PathBuf::from(&match_opts.free[0]);
+    let bytes = std::fs::read(&rom_path).unwrap();
     let rom = Rom::new(&bytes).unwrap();
+    let rom_hash = savestate::rom_hash(&bytes);
 
-    let mut frame = Frame::new();
+    let mut battery = rom.has_battery.then(|| BatterySave::for_rom(&rom_path));
+    let saved_ram = battery.as_ref().map(|battery| battery.saved().to_vec());
 
     let mut key_map = HashMap::new();
     key_map.insert(Keycode::Down, joypad::JoypadButton::DOWN);
@@ -101,24 +125,89 @@ fn main() {
     key_map.insert(Keycode::Z, joypad::JoypadButton::BUTTON_A);
     key_map.insert(Keycode::X, joypad::JoypadButton::BUTTON_B);
 
+    // number keys pick a slot, F5 saves to it and F9 loads from it
+    let mut slot = 0;
+    let state_command = Rc::new(Cell::new(None));
+    let pending_command = state_command.clone();
+
+    // holding backspace steps back through the rewind history, one snapshot per frame
+    let rewinding = Rc::new(Cell::new(false));
+    let is_rewind_held = rewinding.clone();
+    let frame_done = Rc::new(Cell::new(false));
+    let end_of_frame = frame_done.clone();
+
+    // a halted CPU is shown in the title bar until R resets the console
+    let halt_status: Rc<Cell<Option<CpuHalted>>> = Rc::new(Cell::new(None));
+    let title_status = halt_status.clone();
+    let mut title_halted = None;
+
     let apu = NesAPU::new(txs);
 
-    let bus = Bus::new(
+    let mut bus = Bus::new(
         rom,
         move |ppu: &NesPPU, joypad: &mut Joypad| {
-            renderer::render(ppu, &mut frame);
-            texture.update(None, &frame.data, 256 * 3).unwrap();
+            texture.update(None, &ppu.frame.data, 256 * 3).unwrap();
 
             canvas.copy(&texture, None, None).unwrap();
 
             canvas.present();
+
+            if title_status.get() != title_halted {
+                title_halted = title_status.get();
+                let title = match title_halted {
+                    Some(halted) => format!(
+                        "NES Emulator - CPU halted on ${:02X} at ${:04X}, press R to reset",
+                        halted.opcode, halted.pc
+                    ),
+                    None => "NES Emulator".to_string(),
+                };
+                canvas.window_mut().set_title(&title).unwrap();
+            }
+
+            if let Some(battery) = battery.as_mut() {
+                battery.on_frame(&ppu.mapper);
+            }
+            end_of_frame.set(true);
+
             for event in event_pump.poll_iter() {
                 match event {
                     Event::Quit { .. }
                     | Event::KeyDown {
                         keycode: Some(Keycode::Escape),
                         ..
-                    } => std::process::exit(0),
+                    } => {
+                        if let Some(battery) = battery.as_mut() {
+                            battery.flush(&ppu.mapper);
+                        }
+                        std::process::exit(0)
+                    }
+                    Event::KeyDown {
+                        keycode: Some(Keycode::Backspace),
+                        ..
+                    } => is_rewind_held.set(true),
+                    Event::KeyUp {
+                        keycode: Some(Keycode::Backspace),
+                        ..
+                    } => is_rewind_held.set(false),
+                    Event::KeyDown {
+                        keycode: Some(Keycode::F5),
+                        ..
+                    } => pending_command.set(Some(StateCommand::Save(slot))),
+                    Event::KeyDown {
+                        keycode: Some(Keycode::F9),
+                        ..
+                    } => pending_command.set(Some(StateCommand::Load(slot))),
+                    Event::KeyDown {
+                        keycode: Some(Keycode::R),
+                        ..
+                    } => pending_command.set(Some(StateCommand::Reset)),
+                    Event::KeyDown {
+                        keycode: Some(keycode),
+                        ..
+                    } if slot_for_key(keycode).is_some() => {
+                        slot = slot_for_key(keycode).unwrap();
+                        println!("Selected save slot {}", slot);
+                    }
                     Event::KeyDown { keycode, .. } => {
                         if let Some(key) = key_map.get(&keycode.unwrap_or(Keycode::Ampersand)) {
                             joypad.set_button_pressed_status(*key, true);
@@ -135,16 +224,64 @@ fn main() {
         },
         apu,
     );
+    if let Some(ram) = saved_ram {
+        bus.mapper().borrow_mut().load_prg_ram(&ram);
+    }
+    bus.set_ram_init(ram_init);
+
     let mut cpu = CPU::new(bus);
-    cpu.reset();
-    
-    if match_opts.opt_present("t") {
-        cpu.run_with_callback(|cpu| {
+    cpu.power_on();
+
+    let is_trace = match_opts.opt_present("t");
+    let mut rewind = (rewind_mb > 0).then(|| Rewind::new(rewind_mb * 1024 * 1024, rewind_interval));
+    cpu.run_with_callback(move |cpu| {
+        if cpu.halted() != halt_status.get() {
+            halt_status.set(cpu.halted());
+            if let Some(halted) = cpu.halted() {
+                eprintln!("CPU halted on opcode ${:02X} at ${:04X}", halted.opcode, halted.pc);
+            }
+        }
+
+        if is_trace && cpu.halted().is_none() {
             println!("{}", trace::trace(cpu));
-        });
-    } else {
-        cpu.run();
-    }
+        }
+
+        if let Some(rewind) = rewind.as_mut() {
+            if frame_done.take() {
+                if rewinding.get() {
+                    rewind.step_back(cpu);
+                } else {
+                    rewind.on_frame(cpu);
+                }
+            }
+        }
+
+        match state_command.take() {
+            Some(StateCommand::Save(slot)) => {
+                let path = rom_path.with_extension(format!("state{}", slot));
+                let data = savestate::save(cpu, rom_hash);
+                match std::fs::write(&path, data) {
+                    Ok(()) => println!("Saved state to slot {}", slot),
+                    Err(err) => eprintln!("Failed to write {}: {}", path.display(), err),
+                }
+            }
+            Some(StateCommand::Load(slot)) => {
+                let path = rom_path.with_extension(format!("state{}", slot));
+                let result = std::fs::read(&path)
+                    .map_err(|err| err.to_string())
+                    .and_then(|data| savestate::load(cpu, &data, rom_hash));
+                match result {
+                    Ok(()) => println!("Loaded state from slot {}", slot),
+                    Err(err) => eprintln!("Failed to load {}: {}", path.display(), err),
+                }
+            }
+            Some(StateCommand::Reset) => {
+                cpu.soft_reset();
+                println!("Reset");
+            }
+            None => {}
+        }
+    });
     // cpu.program_counter = 0xC000;
 }
 
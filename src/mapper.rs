@@ -0,0 +1,139 @@
+mod axrom;
+mod cnrom;
+mod gxrom;
+mod mmc1;
+mod mmc3;
+mod nrom;
+mod prg_ram;
+mod uxrom;
+
+use std::{cell::RefCell, rc::Rc};
+
+use crate::cartridge::{Mirroring, Rom};
+use crate::savestate::Snapshot;
+pub use axrom::Axrom;
+pub use cnrom::Cnrom;
+pub use gxrom::Gxrom;
+pub use mmc1::Mmc1;
+pub use mmc3::Mmc3;
+pub use nrom::Nrom;
+use prg_ram::PrgRam;
+pub use uxrom::Uxrom;
+
+// Cartridge hardware as seen from the CPU ($4020-$FFFF) and the PPU ($0000-$1FFF).
+// The bus owns the mapper and shares it with the PPU for pattern table access.
+// Snapshots cover bank registers and any cartridge RAM, but not the ROM itself.
+pub trait Mapper: Snapshot {
+    // None when nothing on the cartridge drives the data bus for this address.
+    fn cpu_read(&mut self, addr: u16) -> Option<u8>;
+    fn cpu_write(&mut self, addr: u16, data: u8);
+    // How the bus delivers writes, with the CPU cycle they happen on, for boards
+    // that react to write timing.
+    fn cpu_write_at(&mut self, addr: u16, data: u8, _cycle: usize) {
+        self.cpu_write(addr, data);
+    }
+    fn ppu_read(&mut self, addr: u16) -> u8;
+    fn ppu_write(&mut self, addr: u16, data: u8);
+    fn mirroring(&self) -> Mirroring;
+
+    // Nametable RAM the PPU needs: the console's own 2 KB, plus 2 KB on four-screen boards.
+    fn nametable_ram_size(&self) -> usize {
+        if self.mirroring() == Mirroring::FOUR_SCREEN {
+            0x1000
+        } else {
+            0x0800
+        }
+    }
+
+    // Offset into nametable RAM for a $2000-$2FFF address, as wired on the board.
+    fn nametable_addr(&self, addr: u16) -> u16 {
+        let table = (addr >> 10) & 0b11;
+        self.mirroring().nametable_pages()[table as usize] * 0x400 + (addr & 0x3ff)
+    }
+
+    // Contents of the $6000-$7FFF work RAM, persisted for battery-backed carts.
+    fn prg_ram(&self) -> &[u8];
+    fn load_prg_ram(&mut self, data: &[u8]);
+
+    // Called with every address the PPU puts on its bus while fetching pattern data.
+    fn ppu_bus_address(&mut self, _addr: u16) {}
+
+    fn irq_pending(&self) -> bool {
+        false
+    }
+
+    // Puts the bank and IRQ registers back to their power-on values. PRG RAM and
+    // CHR RAM keep their contents.
+    fn power_on(&mut self) {}
+}
+
+pub type SharedMapper = Rc<RefCell<dyn Mapper>>;
+
+pub fn is_supported(mapper: u16) -> bool {
+    matches!(mapper, 0 | 1 | 2 | 3 | 4 | 7 | 66)
+}
+
+pub fn new(rom: Rom) -> SharedMapper {
+    match rom.mapper {
+        0 => Rc::new(RefCell::new(Nrom::new(rom))),
+        1 => Rc::new(RefCell::new(Mmc1::new(rom))),
+        2 => Rc::new(RefCell::new(Uxrom::new(rom))),
+        3 => Rc::new(RefCell::new(Cnrom::new(rom))),
+        4 => Rc::new(RefCell::new(Mmc3::new(rom))),
+        7 => Rc::new(RefCell::new(Axrom::new(rom))),
+        66 => Rc::new(RefCell::new(Gxrom::new(rom))),
+        id => panic!("unsupported mapper {}", id),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartridge::test::ines_image;
+    use crate::ppu::NesPPU;
+
+    // $2123, $2523, $2923 and $2D23 mapped into nametable RAM
+    fn nametable_addrs(mapper: &SharedMapper) -> [u16; 4] {
+        let mapper = mapper.borrow();
+        [0x2123, 0x2523, 0x2923, 0x2d23].map(|addr| mapper.nametable_addr(addr))
+    }
+
+    fn nrom(flags6: u8) -> SharedMapper {
+        let mut raw = ines_image(0, 1, 1);
+        raw[6] |= flags6;
+        new(Rom::new(&raw).unwrap())
+    }
+
+    #[test]
+    fn test_nametable_pages() {
+        assert_eq!(Mirroring::HORIZONTAL.nametable_pages(), [0, 0, 1, 1]);
+        assert_eq!(Mirroring::VERTICAL.nametable_pages(), [0, 1, 0, 1]);
+        assert_eq!(Mirroring::SINGLE_SCREEN_A.nametable_pages(), [0, 0, 0, 0]);
+        assert_eq!(Mirroring::SINGLE_SCREEN_B.nametable_pages(), [1, 1, 1, 1]);
+        assert_eq!(Mirroring::FOUR_SCREEN.nametable_pages(), [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_horizontal_and_vertical_nametables() {
+        assert_eq!(nametable_addrs(&nrom(0)), [0x123, 0x123, 0x523, 0x523]);
+        assert_eq!(nametable_addrs(&nrom(0b0001)), [0x123, 0x523, 0x123, 0x523]);
+    }
+
+    #[test]
+    fn test_single_screen_nametables() {
+        let mapper = new(Rom::new(&ines_image(7, 2, 0)).unwrap());
+        assert_eq!(nametable_addrs(&mapper), [0x123; 4]);
+        mapper.borrow_mut().cpu_write(0x8000, 0b1_0000);
+        assert_eq!(nametable_addrs(&mapper), [0x523; 4]);
+    }
+
+    #[test]
+    fn test_four_screen_nametables() {
+        let mapper = nrom(0b1000);
+        assert_eq!(mapper.borrow().nametable_ram_size(), 0x1000);
+        assert_eq!(nametable_addrs(&mapper), [0x123, 0x523, 0x923, 0xd23]);
+        assert_eq!(NesPPU::new(mapper).vram.len(), 0x1000);
+
+        assert_eq!(nrom(0).borrow().nametable_ram_size(), 0x0800);
+    }
+}
@@ -0,0 +1,133 @@
+use super::{Mapper, PrgRam};
+use crate::cartridge::{Mirroring, Rom};
+use crate::savestate::{Snapshot, StateReader, StateWriter};
+
+const PRG_BANK_SIZE: usize = 32 * 1024;
+
+// Mapper 7: switchable 32K PRG bank and single-screen mirroring selected by the same register.
+// https://www.nesdev.org/wiki/AxROM
+pub struct Axrom {
+    prg_rom: Vec<u8>,
+    prg_ram: PrgRam,
+    chr: Vec<u8>,
+    is_chr_ram: bool,
+
+    // 7  bit  0
+    // ---- ----
+    // xxxM xPPP
+    //    |  |||
+    //    |  +++- Select 32 KB PRG ROM bank for CPU $8000-$FFFF
+    //    +------ Select 1 KB VRAM page for all 4 nametables
+    bank_select: u8,
+}
+
+impl Axrom {
+    pub fn new(rom: Rom) -> Self {
+        let prg_ram = PrgRam::new(&rom);
+        Axrom {
+            prg_rom: rom.prg_rom,
+            prg_ram,
+            chr: rom.chr_rom,
+            is_chr_ram: rom.is_chr_ram,
+            bank_select: 0,
+        }
+    }
+}
+
+impl Mapper for Axrom {
+    fn cpu_read(&mut self, addr: u16) -> Option<u8> {
+        match addr {
+            0x6000..=0x7FFF => self.prg_ram.read(addr),
+            0x8000..=0xFFFF => {
+                let bank_count = (self.prg_rom.len() / PRG_BANK_SIZE).max(1);
+                let bank = (self.bank_select & 0b111) as usize % bank_count;
+                let index = (bank * PRG_BANK_SIZE + (addr - 0x8000) as usize) % self.prg_rom.len();
+                Some(self.prg_rom[index])
+            }
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x6000..=0x7FFF => self.prg_ram.write(addr, data),
+            0x8000..=0xFFFF => {
+                self.bank_select = data;
+            }
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        self.chr[addr as usize]
+    }
+
+    fn ppu_write(&mut self, addr: u16, data: u8) {
+        // writes to CHR ROM are ignored
+        if self.is_chr_ram {
+            self.chr[addr as usize] = data;
+        }
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        if self.bank_select & 0b1_0000 == 0 {
+            Mirroring::SINGLE_SCREEN_A
+        } else {
+            Mirroring::SINGLE_SCREEN_B
+        }
+    }
+
+    fn prg_ram(&self) -> &[u8] {
+        self.prg_ram.data()
+    }
+
+    fn load_prg_ram(&mut self, data: &[u8]) {
+        self.prg_ram.load(data);
+    }
+
+    fn power_on(&mut self) {
+        self.bank_select = 0;
+    }
+}
+
+impl Snapshot for Axrom {
+    fn save_state(&self, w: &mut StateWriter) {
+        self.prg_ram.save_state(w);
+        if self.is_chr_ram {
+            w.write_bytes(&self.chr);
+        }
+        w.write_u8(self.bank_select);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), String> {
+        self.prg_ram.load_state(r)?;
+        if self.is_chr_ram {
+            r.read_into(&mut self.chr)?;
+        }
+        self.bank_select = r.read_u8()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartridge::test::ines_image;
+
+    #[test]
+    fn test_bank_select_switches_prg_and_nametable() {
+        let mut mapper = Axrom::new(Rom::new(&ines_image(7, 16, 0)).unwrap());
+        assert_eq!(mapper.cpu_read(0x8000), Some(0));
+        assert_eq!(mapper.mirroring(), Mirroring::SINGLE_SCREEN_A);
+
+        mapper.cpu_write(0x8000, 0b1_0101);
+        assert_eq!(mapper.cpu_read(0x8000), Some(5 * 32));
+        assert_eq!(mapper.cpu_read(0xFFFF), Some(5 * 32 + 31));
+        assert_eq!(mapper.mirroring(), Mirroring::SINGLE_SCREEN_B);
+
+        // only three bits select the bank
+        mapper.cpu_write(0x8000, 0b1110_1010);
+        assert_eq!(mapper.cpu_read(0x8000), Some(2 * 32));
+        assert_eq!(mapper.mirroring(), Mirroring::SINGLE_SCREEN_A);
+    }
+}
@@ -0,0 +1,109 @@
+use super::{Mapper, PrgRam};
+use crate::cartridge::{Mirroring, Rom};
+use crate::savestate::{Snapshot, StateReader, StateWriter};
+
+const CHR_BANK_SIZE: usize = 8 * 1024;
+
+// Mapper 3: fixed PRG ROM as in NROM, switchable 8K CHR ROM bank.
+// https://www.nesdev.org/wiki/CNROM
+pub struct Cnrom {
+    prg_rom: Vec<u8>,
+    prg_ram: PrgRam,
+    chr_rom: Vec<u8>,
+    mirroring: Mirroring,
+    chr_bank: u8,
+}
+
+impl Cnrom {
+    pub fn new(rom: Rom) -> Self {
+        let prg_ram = PrgRam::new(&rom);
+        Cnrom {
+            prg_rom: rom.prg_rom,
+            prg_ram,
+            chr_rom: rom.chr_rom,
+            mirroring: rom.screen_mirroring,
+            chr_bank: 0,
+        }
+    }
+}
+
+impl Mapper for Cnrom {
+    fn cpu_read(&mut self, addr: u16) -> Option<u8> {
+        match addr {
+            0x6000..=0x7FFF => self.prg_ram.read(addr),
+            0x8000..=0xFFFF => {
+                let index = (addr - 0x8000) as usize % self.prg_rom.len();
+                Some(self.prg_rom[index])
+            }
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x6000..=0x7FFF => self.prg_ram.write(addr, data),
+            0x8000..=0xFFFF => {
+                // bus conflict: the ROM drives the data bus at the same time as the CPU
+                self.chr_bank = data & self.cpu_read(addr).unwrap_or(data);
+            }
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        let bank_count = (self.chr_rom.len() / CHR_BANK_SIZE).max(1);
+        let bank = self.chr_bank as usize % bank_count;
+        self.chr_rom[(bank * CHR_BANK_SIZE + addr as usize) % self.chr_rom.len()]
+    }
+
+    // CHR ROM only, writes are ignored
+    fn ppu_write(&mut self, _addr: u16, _data: u8) {}
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn prg_ram(&self) -> &[u8] {
+        self.prg_ram.data()
+    }
+
+    fn load_prg_ram(&mut self, data: &[u8]) {
+        self.prg_ram.load(data);
+    }
+
+    fn power_on(&mut self) {
+        self.chr_bank = 0;
+    }
+}
+
+impl Snapshot for Cnrom {
+    fn save_state(&self, w: &mut StateWriter) {
+        self.prg_ram.save_state(w);
+        w.write_u8(self.chr_bank);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), String> {
+        self.prg_ram.load_state(r)?;
+        self.chr_bank = r.read_u8()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartridge::test::ines_image;
+
+    #[test]
+    fn test_bank_select_switches_chr() {
+        let mut mapper = Cnrom::new(Rom::new(&ines_image(3, 1, 4)).unwrap());
+        assert_eq!(mapper.ppu_read(0x0000), 0);
+
+        // $8C00 holds $03, the ROM only lets through the bits it agrees with
+        mapper.cpu_write(0x8C00, 2);
+        assert_eq!(mapper.ppu_read(0x0000), 2 * 8);
+        assert_eq!(mapper.ppu_read(0x1FFF), 2 * 8 + 7);
+        mapper.cpu_write(0x8C00, 0x04);
+        assert_eq!(mapper.ppu_read(0x0000), 0);
+    }
+}
@@ -0,0 +1,126 @@
+use super::{Mapper, PrgRam};
+use crate::cartridge::{Mirroring, Rom};
+use crate::savestate::{Snapshot, StateReader, StateWriter};
+
+const PRG_BANK_SIZE: usize = 32 * 1024;
+const CHR_BANK_SIZE: usize = 8 * 1024;
+
+// Mapper 66: switchable 32K PRG bank and 8K CHR bank.
+// https://www.nesdev.org/wiki/GxROM
+pub struct Gxrom {
+    prg_rom: Vec<u8>,
+    prg_ram: PrgRam,
+    chr_rom: Vec<u8>,
+    mirroring: Mirroring,
+
+    // 7  bit  0
+    // ---- ----
+    // xxPP xxCC
+    //   ||   ||
+    //   ||   ++- Select 8 KB CHR ROM bank for PPU $0000-$1FFF
+    //   ++------ Select 32 KB PRG ROM bank for CPU $8000-$FFFF
+    bank_select: u8,
+}
+
+impl Gxrom {
+    pub fn new(rom: Rom) -> Self {
+        let prg_ram = PrgRam::new(&rom);
+        Gxrom {
+            prg_rom: rom.prg_rom,
+            prg_ram,
+            chr_rom: rom.chr_rom,
+            mirroring: rom.screen_mirroring,
+            bank_select: 0,
+        }
+    }
+}
+
+impl Mapper for Gxrom {
+    fn cpu_read(&mut self, addr: u16) -> Option<u8> {
+        match addr {
+            0x6000..=0x7FFF => self.prg_ram.read(addr),
+            0x8000..=0xFFFF => {
+                let bank_count = (self.prg_rom.len() / PRG_BANK_SIZE).max(1);
+                let bank = ((self.bank_select >> 4) & 0b11) as usize % bank_count;
+                let index = (bank * PRG_BANK_SIZE + (addr - 0x8000) as usize) % self.prg_rom.len();
+                Some(self.prg_rom[index])
+            }
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x6000..=0x7FFF => self.prg_ram.write(addr, data),
+            0x8000..=0xFFFF => {
+                self.bank_select = data;
+            }
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        let bank_count = (self.chr_rom.len() / CHR_BANK_SIZE).max(1);
+        let bank = (self.bank_select & 0b11) as usize % bank_count;
+        self.chr_rom[(bank * CHR_BANK_SIZE + addr as usize) % self.chr_rom.len()]
+    }
+
+    // CHR ROM only, writes are ignored
+    fn ppu_write(&mut self, _addr: u16, _data: u8) {}
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn prg_ram(&self) -> &[u8] {
+        self.prg_ram.data()
+    }
+
+    fn load_prg_ram(&mut self, data: &[u8]) {
+        self.prg_ram.load(data);
+    }
+
+    fn power_on(&mut self) {
+        self.bank_select = 0;
+    }
+}
+
+impl Snapshot for Gxrom {
+    fn save_state(&self, w: &mut StateWriter) {
+        self.prg_ram.save_state(w);
+        w.write_u8(self.bank_select);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), String> {
+        self.prg_ram.load_state(r)?;
+        self.bank_select = r.read_u8()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartridge::test::ines_image;
+
+    #[test]
+    fn test_bank_select_switches_prg_and_chr() {
+        let mut mapper = Gxrom::new(Rom::new(&ines_image(66, 8, 4)).unwrap());
+        assert_eq!(mapper.cpu_read(0x8000), Some(0));
+        assert_eq!(mapper.ppu_read(0x0000), 0);
+
+        mapper.cpu_write(0x8000, 0b0010_0011);
+        assert_eq!(mapper.cpu_read(0x8000), Some(2 * 32));
+        assert_eq!(mapper.cpu_read(0xFFFF), Some(2 * 32 + 31));
+        assert_eq!(mapper.ppu_read(0x0000), 3 * 8);
+        assert_eq!(mapper.ppu_read(0x1FFF), 3 * 8 + 7);
+    }
+
+    #[test]
+    fn test_bank_select_wraps_around_small_roms() {
+        let mut mapper = Gxrom::new(Rom::new(&ines_image(66, 2, 1)).unwrap());
+        mapper.cpu_write(0x8000, 0b0011_0011);
+        assert_eq!(mapper.cpu_read(0x8000), Some(0));
+        assert_eq!(mapper.ppu_read(0x0000), 0);
+    }
+}
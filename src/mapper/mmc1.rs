@@ -0,0 +1,347 @@
+use super::{Mapper, PrgRam};
+use crate::cartridge::{Mirroring, Rom};
+use crate::savestate::{Snapshot, StateReader, StateWriter};
+
+const PRG_BANK_SIZE: usize = 16 * 1024;
+const CHR_BANK_SIZE: usize = 4 * 1024;
+
+// Mapper 1: registers are loaded one bit at a time through a 5-bit shift register.
+// https://www.nesdev.org/wiki/MMC1
+pub struct Mmc1 {
+    prg_rom: Vec<u8>,
+    chr: Vec<u8>,
+    is_chr_ram: bool,
+    prg_ram: PrgRam,
+
+    shift_register: u8,
+    shift_count: u8,
+    // CPU cycle of the last write to the serial port
+    last_write_cycle: Option<usize>,
+
+    // 4bit0
+    // -----
+    // CPPMM
+    // |||||
+    // |||++- Mirroring (0: one-screen, lower bank; 1: one-screen, upper bank;
+    // |||               2: vertical; 3: horizontal)
+    // |++--- PRG ROM bank mode (0, 1: switch 32 KB at $8000, ignoring low bit of bank number;
+    // |                         2: fix first bank at $8000 and switch 16 KB bank at $C000;
+    // |                         3: fix last bank at $C000 and switch 16 KB bank at $8000)
+    // +----- CHR ROM bank mode (0: switch 8 KB at a time; 1: switch two separate 4 KB banks)
+    control: u8,
+    chr_bank0: u8,
+    chr_bank1: u8,
+    prg_bank: u8,
+}
+
+impl Mmc1 {
+    pub fn new(rom: Rom) -> Self {
+        let prg_ram = PrgRam::new(&rom);
+        Mmc1 {
+            prg_rom: rom.prg_rom,
+            chr: rom.chr_rom,
+            is_chr_ram: rom.is_chr_ram,
+            prg_ram,
+            shift_register: 0,
+            shift_count: 0,
+            last_write_cycle: None,
+            // power-on state fixes the last bank at $C000
+            control: 0x0C,
+            chr_bank0: 0,
+            chr_bank1: 0,
+            prg_bank: 0,
+        }
+    }
+
+    fn write_register(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x8000..=0x9FFF => self.control = data,
+            0xA000..=0xBFFF => self.chr_bank0 = data,
+            0xC000..=0xDFFF => self.chr_bank1 = data,
+            0xE000..=0xFFFF => self.prg_bank = data,
+            _ => unreachable!(),
+        }
+    }
+
+    fn prg_ram_enabled(&self) -> bool {
+        self.prg_bank & 0b1_0000 == 0
+    }
+
+    fn prg_offset(&self, addr: u16) -> usize {
+        // a NES 2.0 header can declare less than one bank
+        let bank_count = (self.prg_rom.len() / PRG_BANK_SIZE).max(1);
+        let bank = (self.prg_bank & 0b0_1111) as usize;
+        let (lo_bank, hi_bank) = match (self.control >> 2) & 0b11 {
+            0 | 1 => (bank & !1, bank | 1),
+            2 => (0, bank),
+            3 => (bank, bank_count - 1),
+            _ => unreachable!(),
+        };
+        let (bank, offset) = match addr {
+            0x8000..=0xBFFF => (lo_bank, addr - 0x8000),
+            _ => (hi_bank, addr - 0xC000),
+        };
+        ((bank % bank_count) * PRG_BANK_SIZE + offset as usize) % self.prg_rom.len()
+    }
+
+    fn chr_offset(&self, addr: u16) -> usize {
+        let bank_count = (self.chr.len() / CHR_BANK_SIZE).max(1);
+        let bank = if self.control & 0b1_0000 == 0 {
+            (self.chr_bank0 & !1) as usize + (addr as usize / CHR_BANK_SIZE)
+        } else if addr < 0x1000 {
+            self.chr_bank0 as usize
+        } else {
+            self.chr_bank1 as usize
+        };
+        ((bank % bank_count) * CHR_BANK_SIZE + (addr as usize % CHR_BANK_SIZE)) % self.chr.len()
+    }
+}
+
+impl Mapper for Mmc1 {
+    fn cpu_read(&mut self, addr: u16) -> Option<u8> {
+        match addr {
+            0x6000..=0x7FFF if self.prg_ram_enabled() => self.prg_ram.read(addr),
+            0x8000..=0xFFFF => Some(self.prg_rom[self.prg_offset(addr)]),
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x6000..=0x7FFF if self.prg_ram_enabled() => {
+                self.prg_ram.write(addr, data);
+            }
+            0x8000..=0xFFFF => {
+                if data & 0b1000_0000 != 0 {
+                    self.shift_register = 0;
+                    self.shift_count = 0;
+                    self.control |= 0x0C;
+                    return;
+                }
+
+                self.shift_register |= (data & 1) << self.shift_count;
+                self.shift_count += 1;
+                if self.shift_count == 5 {
+                    self.write_register(addr, self.shift_register);
+                    self.shift_register = 0;
+                    self.shift_count = 0;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn cpu_write_at(&mut self, addr: u16, data: u8, cycle: usize) {
+        if addr >= 0x8000 {
+            // a write on the cycle right after another one is ignored, so only the
+            // dummy write of a read-modify-write instruction reaches the shift register
+            let consecutive = self.last_write_cycle == Some(cycle.wrapping_sub(1));
+            self.last_write_cycle = Some(cycle);
+            if consecutive {
+                return;
+            }
+        }
+        self.cpu_write(addr, data);
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        self.chr[self.chr_offset(addr)]
+    }
+
+    fn ppu_write(&mut self, addr: u16, data: u8) {
+        // writes to CHR ROM are ignored
+        if self.is_chr_ram {
+            let offset = self.chr_offset(addr);
+            self.chr[offset] = data;
+        }
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        match self.control & 0b11 {
+            0 => Mirroring::SINGLE_SCREEN_A,
+            1 => Mirroring::SINGLE_SCREEN_B,
+            2 => Mirroring::VERTICAL,
+            3 => Mirroring::HORIZONTAL,
+            _ => unreachable!(),
+        }
+    }
+
+    fn prg_ram(&self) -> &[u8] {
+        self.prg_ram.data()
+    }
+
+    fn load_prg_ram(&mut self, data: &[u8]) {
+        self.prg_ram.load(data);
+    }
+
+    fn power_on(&mut self) {
+        self.shift_register = 0;
+        self.shift_count = 0;
+        self.last_write_cycle = None;
+        self.control = 0x0C;
+        self.chr_bank0 = 0;
+        self.chr_bank1 = 0;
+        self.prg_bank = 0;
+    }
+}
+
+impl Snapshot for Mmc1 {
+    fn save_state(&self, w: &mut StateWriter) {
+        self.prg_ram.save_state(w);
+        if self.is_chr_ram {
+            w.write_bytes(&self.chr);
+        }
+        w.write_u8(self.shift_register);
+        w.write_u8(self.shift_count);
+        w.write_bool(self.last_write_cycle.is_some());
+        w.write_usize(self.last_write_cycle.unwrap_or(0));
+        w.write_u8(self.control);
+        w.write_u8(self.chr_bank0);
+        w.write_u8(self.chr_bank1);
+        w.write_u8(self.prg_bank);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), String> {
+        self.prg_ram.load_state(r)?;
+        if self.is_chr_ram {
+            r.read_into(&mut self.chr)?;
+        }
+        self.shift_register = r.read_u8()?;
+        self.shift_count = r.read_u8()?;
+        let has_write = r.read_bool()?;
+        let last_write_cycle = r.read_usize()?;
+        self.last_write_cycle = has_write.then_some(last_write_cycle);
+        self.control = r.read_u8()?;
+        self.chr_bank0 = r.read_u8()?;
+        self.chr_bank1 = r.read_u8()?;
+        self.prg_bank = r.read_u8()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apu::{sounds::SoundManager, NesAPU};
+    use crate::bus::Bus;
+    use crate::cartridge::test::ines_image;
+    use crate::cpu::{Mem, CPU};
+    use crate::joypad::Joypad;
+    use crate::ppu::NesPPU;
+
+    fn mmc1() -> Mmc1 {
+        Mmc1::new(Rom::new(&ines_image(1, 8, 2)).unwrap())
+    }
+
+    // registers take 5 writes of one bit each, low bit first
+    fn write_serial(mapper: &mut Mmc1, addr: u16, value: u8) {
+        for i in 0..5 {
+            mapper.cpu_write(addr, (value >> i) & 1);
+        }
+    }
+
+    #[test]
+    fn test_serial_load_switches_bank_on_fifth_write() {
+        let mut mapper = mmc1();
+        assert_eq!(mapper.cpu_read(0x8000), Some(0));
+        assert_eq!(mapper.cpu_read(0xC000), Some(7 * 16));
+
+        for i in 0..4 {
+            mapper.cpu_write(0xE000, (3 >> i) & 1);
+        }
+        assert_eq!(mapper.cpu_read(0x8000), Some(0));
+        mapper.cpu_write(0xE000, 0);
+        assert_eq!(mapper.cpu_read(0x8000), Some(3 * 16));
+        assert_eq!(mapper.cpu_read(0xC000), Some(7 * 16));
+    }
+
+    #[test]
+    fn test_reset_bit_clears_shift_register_and_fixes_last_bank() {
+        let mut mapper = mmc1();
+        // PRG mode 2: first bank fixed at $8000, switchable bank at $C000
+        write_serial(&mut mapper, 0x8000, 0b01000);
+        write_serial(&mut mapper, 0xE000, 2);
+        assert_eq!(mapper.cpu_read(0x8000), Some(0));
+        assert_eq!(mapper.cpu_read(0xC000), Some(2 * 16));
+
+        mapper.cpu_write(0xE000, 1);
+        mapper.cpu_write(0xE000, 1);
+        mapper.cpu_write(0xE000, 0x80);
+        // back in mode 3, and the two bits written before the reset are gone
+        assert_eq!(mapper.cpu_read(0x8000), Some(2 * 16));
+        assert_eq!(mapper.cpu_read(0xC000), Some(7 * 16));
+        write_serial(&mut mapper, 0xE000, 5);
+        assert_eq!(mapper.cpu_read(0x8000), Some(5 * 16));
+    }
+
+    #[test]
+    fn test_disabled_prg_ram_is_not_driven() {
+        let mut mapper = mmc1();
+        mapper.cpu_write(0x6000, 0x42);
+        assert_eq!(mapper.cpu_read(0x6000), Some(0x42));
+
+        write_serial(&mut mapper, 0xE000, 0b1_0000);
+        assert_eq!(mapper.cpu_read(0x6000), None);
+        assert_eq!(mapper.cpu_read(0x5000), None);
+    }
+
+    #[test]
+    fn test_write_right_after_another_is_ignored() {
+        let mut image = ines_image(1, 8, 2);
+        // $FFF0 holds $FF, so the dummy write of INC has the reset bit set
+        let last = image.len() - 2 * 0x2000;
+        image[last - 0x10] = 0xff;
+        let (_sound, txs) = SoundManager::new(44100.0);
+        let bus = Bus::new(Rom::new(&image).unwrap(), |_: &NesPPU, _: &mut Joypad| {}, NesAPU::new(txs));
+        let mut cpu = CPU::new(bus);
+        let program = [
+            0xee, 0xf0, 0xff, // INC $FFF0: writes $FF, then $00 on the next cycle
+            0xa9, 0x03, // LDA #3
+            0x8d, 0x00, 0xe0, 0x4a, // STA $E000; LSR A
+            0x8d, 0x00, 0xe0, 0x4a,
+            0x8d, 0x00, 0xe0, 0x4a,
+            0x8d, 0x00, 0xe0, 0x4a,
+            0x8d, 0x00, 0xe0,
+        ];
+        for (addr, data) in program.iter().enumerate() {
+            cpu.bus.mem_write(addr as u16, *data);
+        }
+        cpu.program_counter = 0;
+        for _ in 0..11 {
+            cpu.step();
+        }
+
+        // the $00 didn't shift in a stray bit, so the five writes load bank 3
+        assert_eq!(cpu.bus.mem_read(0x8000), 3 * 16);
+    }
+
+    #[test]
+    fn test_write_cycles_are_only_tracked_for_the_serial_port() {
+        let mut mapper = mmc1();
+        mapper.cpu_write_at(0x6000, 0x42, 10);
+        for (i, cycle) in [11, 20, 30, 40, 50].iter().enumerate() {
+            mapper.cpu_write_at(0xE000, (3 >> i) & 1, *cycle);
+        }
+        assert_eq!(mapper.cpu_read(0x8000), Some(3 * 16));
+        assert_eq!(mapper.cpu_read(0x6000), Some(0x42));
+    }
+
+    #[test]
+    fn test_roms_smaller_than_a_bank_are_mirrored() {
+        // NES 2.0 header declaring 8K of PRG ROM and 2K of CHR ROM
+        let mut image = ines_image(1, 0, 0);
+        image[4] = 13 << 2;
+        image[5] = 11 << 2;
+        image[7] |= 0b0000_1000;
+        image[9] = 0xFF;
+        image.extend((0..0x2000 + 0x800).map(|i| (i / 1024) as u8));
+        let mut mapper = Mmc1::new(Rom::new(&image).unwrap());
+
+        write_serial(&mut mapper, 0xE000, 5);
+        write_serial(&mut mapper, 0xA000, 3);
+        assert_eq!(mapper.cpu_read(0x8000), Some(0));
+        assert_eq!(mapper.cpu_read(0xFFFF), Some(7));
+        assert_eq!(mapper.ppu_read(0x0000), 8);
+        assert_eq!(mapper.ppu_read(0x1FFF), 9);
+    }
+}
@@ -0,0 +1,331 @@
+use super::{Mapper, PrgRam};
+use crate::cartridge::{Mirroring, Rom};
+use crate::savestate::{Snapshot, StateReader, StateWriter};
+
+const PRG_BANK_SIZE: usize = 8 * 1024;
+const CHR_BANK_SIZE: usize = 1024;
+
+// Mapper 4: 8K PRG / 1K CHR banking and a scanline counter clocked by PPU A12.
+// https://www.nesdev.org/wiki/MMC3
+pub struct Mmc3 {
+    prg_rom: Vec<u8>,
+    chr: Vec<u8>,
+    is_chr_ram: bool,
+    prg_ram: PrgRam,
+    four_screen: bool,
+
+    // 7  bit  0
+    // ---- ----
+    // CPMx xRRR
+    // |||   |||
+    // |||   +++- Specify which bank register to update on next write to Bank Data register
+    // |||        (0-1: 2 KB CHR banks, 2-5: 1 KB CHR banks, 6-7: 8 KB PRG banks)
+    // ||+------- Nothing on the MMC3
+    // |+-------- PRG ROM bank mode (0: $8000-$9FFF swappable, $C000-$DFFF fixed to second-last bank;
+    // |                             1: $C000-$DFFF swappable, $8000-$9FFF fixed to second-last bank)
+    // +--------- CHR A12 inversion (0: two 2 KB banks at $0000-$0FFF, four 1 KB banks at $1000-$1FFF;
+    //                               1: two 2 KB banks at $1000-$1FFF, four 1 KB banks at $0000-$0FFF)
+    bank_select: u8,
+    bank_registers: [u8; 8],
+    horizontal_mirroring: bool,
+    prg_ram_protect: u8,
+
+    irq_latch: u8,
+    irq_counter: u8,
+    irq_reload: bool,
+    irq_enabled: bool,
+    irq_pending: bool,
+    last_a12: bool,
+}
+
+impl Mmc3 {
+    pub fn new(rom: Rom) -> Self {
+        let prg_ram = PrgRam::new(&rom);
+        Mmc3 {
+            prg_rom: rom.prg_rom,
+            chr: rom.chr_rom,
+            is_chr_ram: rom.is_chr_ram,
+            prg_ram,
+            four_screen: rom.screen_mirroring == Mirroring::FOUR_SCREEN,
+            bank_select: 0,
+            bank_registers: [0; 8],
+            horizontal_mirroring: rom.screen_mirroring == Mirroring::HORIZONTAL,
+            prg_ram_protect: 0,
+            irq_latch: 0,
+            irq_counter: 0,
+            irq_reload: false,
+            irq_enabled: false,
+            irq_pending: false,
+            last_a12: false,
+        }
+    }
+
+    fn prg_ram_enabled(&self) -> bool {
+        self.prg_ram_protect & 0b1000_0000 != 0
+    }
+
+    fn prg_ram_writable(&self) -> bool {
+        self.prg_ram_enabled() && self.prg_ram_protect & 0b0100_0000 == 0
+    }
+
+    fn prg_offset(&self, addr: u16) -> usize {
+        let bank_count = self.prg_rom.len() / PRG_BANK_SIZE;
+        // a NES 2.0 header can declare a single 8K bank
+        let second_last = bank_count.saturating_sub(2);
+        let swap_mode = self.bank_select & 0b0100_0000 != 0;
+        let bank = match (addr, swap_mode) {
+            (0x8000..=0x9FFF, false) => self.bank_registers[6] as usize,
+            (0x8000..=0x9FFF, true) => second_last,
+            (0xA000..=0xBFFF, _) => self.bank_registers[7] as usize,
+            (0xC000..=0xDFFF, false) => second_last,
+            (0xC000..=0xDFFF, true) => self.bank_registers[6] as usize,
+            _ => bank_count - 1,
+        };
+        (bank % bank_count) * PRG_BANK_SIZE + (addr as usize % PRG_BANK_SIZE)
+    }
+
+    fn chr_offset(&self, addr: u16) -> usize {
+        let bank_count = self.chr.len() / CHR_BANK_SIZE;
+        let addr = if self.bank_select & 0b1000_0000 != 0 {
+            addr ^ 0x1000
+        } else {
+            addr
+        };
+        let bank = match addr {
+            0x0000..=0x07FF => (self.bank_registers[0] & 0xFE) as usize + (addr as usize / CHR_BANK_SIZE),
+            0x0800..=0x0FFF => (self.bank_registers[1] & 0xFE) as usize + (addr as usize / CHR_BANK_SIZE - 2),
+            0x1000..=0x13FF => self.bank_registers[2] as usize,
+            0x1400..=0x17FF => self.bank_registers[3] as usize,
+            0x1800..=0x1BFF => self.bank_registers[4] as usize,
+            _ => self.bank_registers[5] as usize,
+        };
+        (bank % bank_count) * CHR_BANK_SIZE + (addr as usize % CHR_BANK_SIZE)
+    }
+
+    fn clock_irq_counter(&mut self) {
+        if self.irq_counter == 0 || self.irq_reload {
+            self.irq_counter = self.irq_latch;
+            self.irq_reload = false;
+        } else {
+            self.irq_counter -= 1;
+        }
+
+        if self.irq_counter == 0 && self.irq_enabled {
+            self.irq_pending = true;
+        }
+    }
+}
+
+impl Mapper for Mmc3 {
+    fn cpu_read(&mut self, addr: u16) -> Option<u8> {
+        match addr {
+            0x6000..=0x7FFF if self.prg_ram_enabled() => self.prg_ram.read(addr),
+            0x8000..=0xFFFF => Some(self.prg_rom[self.prg_offset(addr)]),
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, data: u8) {
+        let even = addr & 1 == 0;
+        match addr {
+            0x6000..=0x7FFF if self.prg_ram_writable() => {
+                self.prg_ram.write(addr, data);
+            }
+            0x8000..=0x9FFF if even => self.bank_select = data,
+            0x8000..=0x9FFF => {
+                let register = (self.bank_select & 0b111) as usize;
+                self.bank_registers[register] = data;
+            }
+            0xA000..=0xBFFF if even => self.horizontal_mirroring = data & 1 != 0,
+            0xA000..=0xBFFF => self.prg_ram_protect = data,
+            0xC000..=0xDFFF if even => self.irq_latch = data,
+            0xC000..=0xDFFF => {
+                self.irq_counter = 0;
+                self.irq_reload = true;
+            }
+            0xE000..=0xFFFF if even => {
+                self.irq_enabled = false;
+                self.irq_pending = false;
+            }
+            0xE000..=0xFFFF => self.irq_enabled = true,
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        self.chr[self.chr_offset(addr)]
+    }
+
+    fn ppu_write(&mut self, addr: u16, data: u8) {
+        // writes to CHR ROM are ignored
+        if self.is_chr_ram {
+            let offset = self.chr_offset(addr);
+            self.chr[offset] = data;
+        }
+    }
+
+    fn ppu_bus_address(&mut self, addr: u16) {
+        let a12 = addr & 0x1000 != 0;
+        if a12 && !self.last_a12 {
+            self.clock_irq_counter();
+        }
+        self.last_a12 = a12;
+    }
+
+    fn irq_pending(&self) -> bool {
+        self.irq_pending
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        if self.four_screen {
+            Mirroring::FOUR_SCREEN
+        } else if self.horizontal_mirroring {
+            Mirroring::HORIZONTAL
+        } else {
+            Mirroring::VERTICAL
+        }
+    }
+
+    fn prg_ram(&self) -> &[u8] {
+        self.prg_ram.data()
+    }
+
+    fn load_prg_ram(&mut self, data: &[u8]) {
+        self.prg_ram.load(data);
+    }
+
+    fn power_on(&mut self) {
+        // mirroring is left as the game last set it
+        self.bank_select = 0;
+        self.bank_registers = [0; 8];
+        self.prg_ram_protect = 0;
+        self.irq_latch = 0;
+        self.irq_counter = 0;
+        self.irq_reload = false;
+        self.irq_enabled = false;
+        self.irq_pending = false;
+        self.last_a12 = false;
+    }
+}
+
+impl Snapshot for Mmc3 {
+    fn save_state(&self, w: &mut StateWriter) {
+        self.prg_ram.save_state(w);
+        if self.is_chr_ram {
+            w.write_bytes(&self.chr);
+        }
+        w.write_u8(self.bank_select);
+        w.write_bytes(&self.bank_registers);
+        w.write_bool(self.horizontal_mirroring);
+        w.write_u8(self.prg_ram_protect);
+        w.write_u8(self.irq_latch);
+        w.write_u8(self.irq_counter);
+        w.write_bool(self.irq_reload);
+        w.write_bool(self.irq_enabled);
+        w.write_bool(self.irq_pending);
+        w.write_bool(self.last_a12);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), String> {
+        self.prg_ram.load_state(r)?;
+        if self.is_chr_ram {
+            r.read_into(&mut self.chr)?;
+        }
+        self.bank_select = r.read_u8()?;
+        r.read_into(&mut self.bank_registers)?;
+        self.horizontal_mirroring = r.read_bool()?;
+        self.prg_ram_protect = r.read_u8()?;
+        self.irq_latch = r.read_u8()?;
+        self.irq_counter = r.read_u8()?;
+        self.irq_reload = r.read_bool()?;
+        self.irq_enabled = r.read_bool()?;
+        self.irq_pending = r.read_bool()?;
+        self.last_a12 = r.read_bool()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartridge::test::ines_image;
+
+    fn mmc3() -> Mmc3 {
+        Mmc3::new(Rom::new(&ines_image(4, 8, 8)).unwrap())
+    }
+
+    // A12 going from low to high is what clocks the counter
+    fn clock_a12(mapper: &mut Mmc3) {
+        mapper.ppu_bus_address(0x0000);
+        mapper.ppu_bus_address(0x1000);
+    }
+
+    #[test]
+    fn test_prg_mode_swaps_fixed_bank() {
+        let mut mapper = mmc3();
+        mapper.cpu_write(0x8000, 6);
+        mapper.cpu_write(0x8001, 2);
+        assert_eq!(mapper.cpu_read(0x8000), Some(2 * 8));
+        assert_eq!(mapper.cpu_read(0xC000), Some(14 * 8));
+        assert_eq!(mapper.cpu_read(0xE000), Some(15 * 8));
+
+        mapper.cpu_write(0x8000, 0b0100_0110);
+        assert_eq!(mapper.cpu_read(0x8000), Some(14 * 8));
+        assert_eq!(mapper.cpu_read(0xC000), Some(2 * 8));
+        assert_eq!(mapper.cpu_read(0xE000), Some(15 * 8));
+    }
+
+    #[test]
+    fn test_single_prg_bank() {
+        let mut rom = Rom::new(&ines_image(4, 1, 8)).unwrap();
+        rom.prg_rom.truncate(PRG_BANK_SIZE);
+        let mut mapper = Mmc3::new(rom);
+        assert_eq!(mapper.cpu_read(0x8000), Some(0));
+        assert_eq!(mapper.cpu_read(0xE000), Some(0));
+    }
+
+    #[test]
+    fn test_irq_counter_reloads_and_counts_down() {
+        let mut mapper = mmc3();
+        mapper.cpu_write(0xC000, 2);
+        mapper.cpu_write(0xC001, 0);
+        mapper.cpu_write(0xE001, 0);
+
+        // the first clock reloads the counter from the latch
+        clock_a12(&mut mapper);
+        assert!(!mapper.irq_pending());
+        clock_a12(&mut mapper);
+        assert!(!mapper.irq_pending());
+        // staying high is not another clock
+        mapper.ppu_bus_address(0x1000);
+        assert!(!mapper.irq_pending());
+        clock_a12(&mut mapper);
+        assert!(mapper.irq_pending());
+
+        mapper.cpu_write(0xE000, 0);
+        assert!(!mapper.irq_pending());
+        // the counter hit 0, so the next clock reloads it
+        mapper.cpu_write(0xE001, 0);
+        clock_a12(&mut mapper);
+        assert!(!mapper.irq_pending());
+        clock_a12(&mut mapper);
+        clock_a12(&mut mapper);
+        assert!(mapper.irq_pending());
+    }
+
+    #[test]
+    fn test_power_on_restores_banks_and_irq() {
+        let mut mapper = mmc3();
+        mapper.cpu_write(0x8000, 0b0100_0110);
+        mapper.cpu_write(0x8001, 2);
+        mapper.cpu_write(0xC000, 0);
+        mapper.cpu_write(0xE001, 0);
+        clock_a12(&mut mapper);
+        assert!(mapper.irq_pending());
+
+        mapper.power_on();
+        assert!(!mapper.irq_pending());
+        assert_eq!(mapper.cpu_read(0x8000), Some(0));
+        assert_eq!(mapper.cpu_read(0xC000), Some(14 * 8));
+    }
+}
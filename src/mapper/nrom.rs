@@ -0,0 +1,108 @@
+use super::{Mapper, PrgRam};
+use crate::cartridge::{Mirroring, Rom};
+use crate::savestate::{Snapshot, StateReader, StateWriter};
+
+// Mapper 0: 16K or 32K of fixed PRG ROM and 8K of CHR ROM (or RAM).
+pub struct Nrom {
+    prg_rom: Vec<u8>,
+    prg_ram: PrgRam,
+    chr: Vec<u8>,
+    is_chr_ram: bool,
+    mirroring: Mirroring,
+}
+
+impl Nrom {
+    pub fn new(rom: Rom) -> Self {
+        let prg_ram = PrgRam::new(&rom);
+        Nrom {
+            prg_rom: rom.prg_rom,
+            prg_ram,
+            chr: rom.chr_rom,
+            is_chr_ram: rom.is_chr_ram,
+            mirroring: rom.screen_mirroring,
+        }
+    }
+}
+
+impl Mapper for Nrom {
+    fn cpu_read(&mut self, addr: u16) -> Option<u8> {
+        match addr {
+            0x6000..=0x7FFF => self.prg_ram.read(addr),
+            0x8000..=0xFFFF => {
+                // 16K images are mirrored into $C000-$FFFF
+                let index = (addr - 0x8000) as usize % self.prg_rom.len().max(1);
+                self.prg_rom.get(index).copied()
+            }
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, data: u8) {
+        // no registers, writes to ROM are ignored
+        if let 0x6000..=0x7FFF = addr {
+            self.prg_ram.write(addr, data);
+        }
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        self.chr[addr as usize]
+    }
+
+    fn ppu_write(&mut self, addr: u16, data: u8) {
+        // writes to CHR ROM are ignored
+        if self.is_chr_ram {
+            self.chr[addr as usize] = data;
+        }
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn prg_ram(&self) -> &[u8] {
+        self.prg_ram.data()
+    }
+
+    fn load_prg_ram(&mut self, data: &[u8]) {
+        self.prg_ram.load(data);
+    }
+}
+
+impl Snapshot for Nrom {
+    fn save_state(&self, w: &mut StateWriter) {
+        self.prg_ram.save_state(w);
+        if self.is_chr_ram {
+            w.write_bytes(&self.chr);
+        }
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), String> {
+        self.prg_ram.load_state(r)?;
+        if self.is_chr_ram {
+            r.read_into(&mut self.chr)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartridge::test::ines_image;
+
+    #[test]
+    fn test_16k_prg_is_mirrored() {
+        let mut mapper = Nrom::new(Rom::new(&ines_image(0, 1, 1)).unwrap());
+        assert_eq!(mapper.cpu_read(0x8000), Some(0));
+        assert_eq!(mapper.cpu_read(0xC000), Some(0));
+        assert_eq!(mapper.cpu_read(0xFFFF), Some(15));
+    }
+
+    #[test]
+    fn test_missing_prg_is_not_driven() {
+        let mut rom = Rom::new(&ines_image(0, 1, 1)).unwrap();
+        rom.prg_rom.clear();
+        let mut mapper = Nrom::new(rom);
+        assert_eq!(mapper.cpu_read(0x8000), None);
+    }
+}
@@ -0,0 +1,51 @@
+use crate::cartridge::Rom;
+use crate::savestate::{Snapshot, StateReader, StateWriter};
+
+// Cartridge work RAM at $6000-$7FFF, sized from the header and mirrored
+// across the window when smaller than 8K.
+pub struct PrgRam {
+    data: Vec<u8>,
+}
+
+impl PrgRam {
+    pub fn new(rom: &Rom) -> Self {
+        PrgRam {
+            data: vec![0; rom.prg_ram_size + rom.prg_nvram_size],
+        }
+    }
+
+    // None when the board has no RAM to answer.
+    pub fn read(&self, addr: u16) -> Option<u8> {
+        if self.data.is_empty() {
+            return None;
+        }
+        Some(self.data[(addr - 0x6000) as usize % self.data.len()])
+    }
+
+    pub fn write(&mut self, addr: u16, data: u8) {
+        if self.data.is_empty() {
+            return;
+        }
+        let len = self.data.len();
+        self.data[(addr - 0x6000) as usize % len] = data;
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn load(&mut self, data: &[u8]) {
+        let len = self.data.len().min(data.len());
+        self.data[..len].copy_from_slice(&data[..len]);
+    }
+}
+
+impl Snapshot for PrgRam {
+    fn save_state(&self, w: &mut StateWriter) {
+        w.write_bytes(&self.data);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), String> {
+        r.read_into(&mut self.data)
+    }
+}
@@ -0,0 +1,149 @@
+use super::{Mapper, PrgRam};
+use crate::cartridge::{Mirroring, Rom};
+use crate::savestate::{Snapshot, StateReader, StateWriter};
+
+const PRG_BANK_SIZE: usize = 16 * 1024;
+
+// Mapper 2: switchable 16K bank at $8000, last bank fixed at $C000.
+// https://www.nesdev.org/wiki/UxROM
+pub struct Uxrom {
+    prg_rom: Vec<u8>,
+    prg_ram: PrgRam,
+    chr: Vec<u8>,
+    is_chr_ram: bool,
+    mirroring: Mirroring,
+    prg_bank: u8,
+}
+
+impl Uxrom {
+    pub fn new(rom: Rom) -> Self {
+        let prg_ram = PrgRam::new(&rom);
+        Uxrom {
+            prg_rom: rom.prg_rom,
+            prg_ram,
+            chr: rom.chr_rom,
+            is_chr_ram: rom.is_chr_ram,
+            mirroring: rom.screen_mirroring,
+            prg_bank: 0,
+        }
+    }
+}
+
+impl Mapper for Uxrom {
+    fn cpu_read(&mut self, addr: u16) -> Option<u8> {
+        // a NES 2.0 header can declare less than one bank
+        let bank_count = (self.prg_rom.len() / PRG_BANK_SIZE).max(1);
+        let bank = match addr {
+            0x8000..=0xBFFF => self.prg_bank as usize % bank_count,
+            _ => bank_count - 1,
+        };
+        match addr {
+            0x6000..=0x7FFF => self.prg_ram.read(addr),
+            0x8000..=0xFFFF => {
+                let index = (bank * PRG_BANK_SIZE + (addr as usize % PRG_BANK_SIZE)) % self.prg_rom.len();
+                Some(self.prg_rom[index])
+            }
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x6000..=0x7FFF => self.prg_ram.write(addr, data),
+            0x8000..=0xFFFF => {
+                // bus conflict: the ROM drives the data bus at the same time as the CPU
+                self.prg_bank = data & self.cpu_read(addr).unwrap_or(data);
+            }
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        self.chr[addr as usize]
+    }
+
+    fn ppu_write(&mut self, addr: u16, data: u8) {
+        // writes to CHR ROM are ignored
+        if self.is_chr_ram {
+            self.chr[addr as usize] = data;
+        }
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn prg_ram(&self) -> &[u8] {
+        self.prg_ram.data()
+    }
+
+    fn load_prg_ram(&mut self, data: &[u8]) {
+        self.prg_ram.load(data);
+    }
+
+    fn power_on(&mut self) {
+        self.prg_bank = 0;
+    }
+}
+
+impl Snapshot for Uxrom {
+    fn save_state(&self, w: &mut StateWriter) {
+        self.prg_ram.save_state(w);
+        if self.is_chr_ram {
+            w.write_bytes(&self.chr);
+        }
+        w.write_u8(self.prg_bank);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), String> {
+        self.prg_ram.load_state(r)?;
+        if self.is_chr_ram {
+            r.read_into(&mut self.chr)?;
+        }
+        self.prg_bank = r.read_u8()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartridge::test::ines_image;
+
+    #[test]
+    fn test_bank_select_switches_lower_window() {
+        let mut mapper = Uxrom::new(Rom::new(&ines_image(2, 8, 0)).unwrap());
+        assert_eq!(mapper.cpu_read(0x8000), Some(0));
+        assert_eq!(mapper.cpu_read(0xC000), Some(7 * 16));
+
+        // $D400 holds $75, which has all the bits of 5 set
+        mapper.cpu_write(0xD400, 5);
+        assert_eq!(mapper.cpu_read(0x8000), Some(5 * 16));
+        assert_eq!(mapper.cpu_read(0xC000), Some(7 * 16));
+    }
+
+    #[test]
+    fn test_bank_select_bus_conflict() {
+        let mut mapper = Uxrom::new(Rom::new(&ines_image(2, 8, 0)).unwrap());
+        mapper.cpu_write(0xD400, 5);
+        // $75 & $0A leaves nothing
+        mapper.cpu_write(0xD400, 0x0A);
+        assert_eq!(mapper.cpu_read(0x8000), Some(0));
+    }
+
+    #[test]
+    fn test_prg_smaller_than_a_bank_is_mirrored() {
+        // NES 2.0 header declaring 8K of PRG ROM as 2^13 * 1 bytes
+        let mut image = ines_image(2, 0, 0);
+        image[4] = 13 << 2;
+        image[7] |= 0b0000_1000;
+        image[9] = 0x0F;
+        image.extend((0..0x2000).map(|i| (i / 1024) as u8));
+        let mut mapper = Uxrom::new(Rom::new(&image).unwrap());
+
+        mapper.cpu_write(0x8000, 3);
+        assert_eq!(mapper.cpu_read(0x8000), Some(0));
+        assert_eq!(mapper.cpu_read(0xA000), Some(0));
+        assert_eq!(mapper.cpu_read(0xFFFF), Some(7));
+    }
+}
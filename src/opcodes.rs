@@ -1,369 +1,465 @@
-use std::collections::HashMap;
-
 use once_cell::sync::Lazy;
 
 use crate::cpu::AddressingMode;
 
+// What an opcode does, whatever its addressing mode. The unofficial *NOP and *SBC
+// behave like their official namesakes and share their variants.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Instruction {
+    ADC, AND, ASL, BCC, BCS, BEQ, BIT, BMI, BNE, BPL, BRK, BVC, BVS, CLC,
+    CLD, CLI, CLV, CMP, CPX, CPY, DEC, DEX, DEY, EOR, INC, INX, INY, JMP,
+    JSR, LDA, LDX, LDY, LSR, NOP, ORA, PHA, PHP, PLA, PLP, ROL, ROR, RTI,
+    RTS, SBC, SEC, SED, SEI, STA, STX, STY, TAX, TAY, TSX, TXA, TXS, TYA,
+    /* unofficial */
+    AHX, ALR, ANC, ARR, AXS, DCP, ISB, JAM, LAS, LAX, LXA, RLA, RRA, SAX,
+    SHX, SHY, SLO, SRE, TAS, XAA,
+}
+
+impl Instruction {
+    fn from_name(name: &str) -> Self {
+        match name.trim_start_matches('*') {
+            "ADC" => Instruction::ADC,
+            "AND" => Instruction::AND,
+            "ASL" => Instruction::ASL,
+            "BCC" => Instruction::BCC,
+            "BCS" => Instruction::BCS,
+            "BEQ" => Instruction::BEQ,
+            "BIT" => Instruction::BIT,
+            "BMI" => Instruction::BMI,
+            "BNE" => Instruction::BNE,
+            "BPL" => Instruction::BPL,
+            "BRK" => Instruction::BRK,
+            "BVC" => Instruction::BVC,
+            "BVS" => Instruction::BVS,
+            "CLC" => Instruction::CLC,
+            "CLD" => Instruction::CLD,
+            "CLI" => Instruction::CLI,
+            "CLV" => Instruction::CLV,
+            "CMP" => Instruction::CMP,
+            "CPX" => Instruction::CPX,
+            "CPY" => Instruction::CPY,
+            "DEC" => Instruction::DEC,
+            "DEX" => Instruction::DEX,
+            "DEY" => Instruction::DEY,
+            "EOR" => Instruction::EOR,
+            "INC" => Instruction::INC,
+            "INX" => Instruction::INX,
+            "INY" => Instruction::INY,
+            "JMP" => Instruction::JMP,
+            "JSR" => Instruction::JSR,
+            "LDA" => Instruction::LDA,
+            "LDX" => Instruction::LDX,
+            "LDY" => Instruction::LDY,
+            "LSR" => Instruction::LSR,
+            "NOP" => Instruction::NOP,
+            "ORA" => Instruction::ORA,
+            "PHA" => Instruction::PHA,
+            "PHP" => Instruction::PHP,
+            "PLA" => Instruction::PLA,
+            "PLP" => Instruction::PLP,
+            "ROL" => Instruction::ROL,
+            "ROR" => Instruction::ROR,
+            "RTI" => Instruction::RTI,
+            "RTS" => Instruction::RTS,
+            "SBC" => Instruction::SBC,
+            "SEC" => Instruction::SEC,
+            "SED" => Instruction::SED,
+            "SEI" => Instruction::SEI,
+            "STA" => Instruction::STA,
+            "STX" => Instruction::STX,
+            "STY" => Instruction::STY,
+            "TAX" => Instruction::TAX,
+            "TAY" => Instruction::TAY,
+            "TSX" => Instruction::TSX,
+            "TXA" => Instruction::TXA,
+            "TXS" => Instruction::TXS,
+            "TYA" => Instruction::TYA,
+            "AHX" => Instruction::AHX,
+            "ALR" => Instruction::ALR,
+            "ANC" => Instruction::ANC,
+            "ARR" => Instruction::ARR,
+            "AXS" => Instruction::AXS,
+            "DCP" => Instruction::DCP,
+            "ISB" => Instruction::ISB,
+            "JAM" => Instruction::JAM,
+            "LAS" => Instruction::LAS,
+            "LAX" => Instruction::LAX,
+            "LXA" => Instruction::LXA,
+            "RLA" => Instruction::RLA,
+            "RRA" => Instruction::RRA,
+            "SAX" => Instruction::SAX,
+            "SHX" => Instruction::SHX,
+            "SHY" => Instruction::SHY,
+            "SLO" => Instruction::SLO,
+            "SRE" => Instruction::SRE,
+            "TAS" => Instruction::TAS,
+            "XAA" => Instruction::XAA,
+            _ => panic!("Unknown instruction {}", name),
+        }
+    }
+}
+
 pub struct OpCode {
     pub code: u8,
     pub name: &'static str,
+    pub instruction: Instruction,
     pub len: u8,
-    pub cycles: u8,
     pub mode: AddressingMode,
 }
 
 impl OpCode {
-    fn new(code: u8, name: &'static str, len: u8, cycles: u8, mode: AddressingMode) -> Self {
+    fn new(code: u8, name: &'static str, len: u8, mode: AddressingMode) -> Self {
         OpCode{
             code,
             name,
+            instruction: Instruction::from_name(name),
             len,
-            cycles,
             mode,
         }
     }
 }
 
 pub static CPU_OPS_CODES: Lazy<Vec<OpCode>> = Lazy::new(|| {vec![
-    OpCode::new(0x69, "ADC", 2, 2, AddressingMode::Immediate),
-    OpCode::new(0x65, "ADC", 2, 3, AddressingMode::ZeroPage),
-    OpCode::new(0x75, "ADC", 2, 4, AddressingMode::ZeroPage_X),
-    OpCode::new(0x6D, "ADC", 3, 4, AddressingMode::Absolute),
-    OpCode::new(0x7D, "ADC", 3, 4, AddressingMode::Absolute_X),
-    OpCode::new(0x79, "ADC", 3, 4, AddressingMode::Absolute_Y),
-    OpCode::new(0x61, "ADC", 2, 6, AddressingMode::Indirect_X),
-    OpCode::new(0x71, "ADC", 2, 5, AddressingMode::Indirect_Y),
+    OpCode::new(0x69, "ADC", 2, AddressingMode::Immediate),
+    OpCode::new(0x65, "ADC", 2, AddressingMode::ZeroPage),
+    OpCode::new(0x75, "ADC", 2, AddressingMode::ZeroPage_X),
+    OpCode::new(0x6D, "ADC", 3, AddressingMode::Absolute),
+    OpCode::new(0x7D, "ADC", 3, AddressingMode::Absolute_X),
+    OpCode::new(0x79, "ADC", 3, AddressingMode::Absolute_Y),
+    OpCode::new(0x61, "ADC", 2, AddressingMode::Indirect_X),
+    OpCode::new(0x71, "ADC", 2, AddressingMode::Indirect_Y),
 
-    OpCode::new(0x29, "AND", 2, 2, AddressingMode::Immediate),
-    OpCode::new(0x25, "AND", 2, 3, AddressingMode::ZeroPage),
-    OpCode::new(0x35, "AND", 2, 4, AddressingMode::ZeroPage_X),
-    OpCode::new(0x2D, "AND", 3, 4, AddressingMode::Absolute),
-    OpCode::new(0x3D, "AND", 3, 4, AddressingMode::Absolute_X),
-    OpCode::new(0x39, "AND", 3, 4, AddressingMode::Absolute_Y),
-    OpCode::new(0x21, "AND", 2, 6, AddressingMode::Indirect_X),
-    OpCode::new(0x31, "AND", 2, 5, AddressingMode::Indirect_Y),
+    OpCode::new(0x29, "AND", 2, AddressingMode::Immediate),
+    OpCode::new(0x25, "AND", 2, AddressingMode::ZeroPage),
+    OpCode::new(0x35, "AND", 2, AddressingMode::ZeroPage_X),
+    OpCode::new(0x2D, "AND", 3, AddressingMode::Absolute),
+    OpCode::new(0x3D, "AND", 3, AddressingMode::Absolute_X),
+    OpCode::new(0x39, "AND", 3, AddressingMode::Absolute_Y),
+    OpCode::new(0x21, "AND", 2, AddressingMode::Indirect_X),
+    OpCode::new(0x31, "AND", 2, AddressingMode::Indirect_Y),
 
-    OpCode::new(0x0A, "ASL", 1, 2, AddressingMode::NoneAddressing),
-    OpCode::new(0x06, "ASL", 2, 5, AddressingMode::ZeroPage),
-    OpCode::new(0x16, "ASL", 2, 6, AddressingMode::ZeroPage_X),
-    OpCode::new(0x0E, "ASL", 3, 6, AddressingMode::Absolute),
-    OpCode::new(0x1E, "ASL", 3, 7, AddressingMode::Absolute_X),
+    OpCode::new(0x0A, "ASL", 1, AddressingMode::NoneAddressing),
+    OpCode::new(0x06, "ASL", 2, AddressingMode::ZeroPage),
+    OpCode::new(0x16, "ASL", 2, AddressingMode::ZeroPage_X),
+    OpCode::new(0x0E, "ASL", 3, AddressingMode::Absolute),
+    OpCode::new(0x1E, "ASL", 3, AddressingMode::Absolute_X),
 
-    OpCode::new(0x90, "BCC", 2, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x90, "BCC", 2, AddressingMode::NoneAddressing),
 
-    OpCode::new(0xB0, "BCS", 2, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0xB0, "BCS", 2, AddressingMode::NoneAddressing),
 
-    OpCode::new(0xF0, "BEQ", 2, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0xF0, "BEQ", 2, AddressingMode::NoneAddressing),
 
-    OpCode::new(0x24, "BIT", 2, 3, AddressingMode::ZeroPage),
-    OpCode::new(0x2C, "BIT", 3, 4, AddressingMode::Absolute),
+    OpCode::new(0x24, "BIT", 2, AddressingMode::ZeroPage),
+    OpCode::new(0x2C, "BIT", 3, AddressingMode::Absolute),
 
-    OpCode::new(0x30, "BMI", 2, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x30, "BMI", 2, AddressingMode::NoneAddressing),
 
-    OpCode::new(0xD0, "BNE", 2, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0xD0, "BNE", 2, AddressingMode::NoneAddressing),
 
-    OpCode::new(0x10, "BPL", 2, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x10, "BPL", 2, AddressingMode::NoneAddressing),
 
-    OpCode::new(0x00, "BRK", 1, 7, AddressingMode::NoneAddressing),
+    OpCode::new(0x00, "BRK", 1, AddressingMode::NoneAddressing),
 
-    OpCode::new(0x50, "BVC", 2, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x50, "BVC", 2, AddressingMode::NoneAddressing),
 
-    OpCode::new(0x70, "BVS", 2, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x70, "BVS", 2, AddressingMode::NoneAddressing),
 
-    OpCode::new(0x18, "CLC", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x18, "CLC", 1, AddressingMode::NoneAddressing),
 
-    OpCode::new(0xD8, "CLD", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0xD8, "CLD", 1, AddressingMode::NoneAddressing),
 
-    OpCode::new(0x58, "CLI", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x58, "CLI", 1, AddressingMode::NoneAddressing),
 
-    OpCode::new(0xB8, "CLV", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0xB8, "CLV", 1, AddressingMode::NoneAddressing),
 
-    OpCode::new(0xC9, "CMP", 2, 2, AddressingMode::Immediate),
-    OpCode::new(0xC5, "CMP", 2, 3, AddressingMode::ZeroPage),
-    OpCode::new(0xD5, "CMP", 2, 4, AddressingMode::ZeroPage_X),
-    OpCode::new(0xCD, "CMP", 3, 4, AddressingMode::Absolute),
-    OpCode::new(0xDD, "CMP", 3, 4, AddressingMode::Absolute_X),
-    OpCode::new(0xD9, "CMP", 3, 4, AddressingMode::Absolute_Y),
-    OpCode::new(0xC1, "CMP", 2, 6, AddressingMode::Indirect_X),
-    OpCode::new(0xD1, "CMP", 2, 5, AddressingMode::Indirect_Y),
+    OpCode::new(0xC9, "CMP", 2, AddressingMode::Immediate),
+    OpCode::new(0xC5, "CMP", 2, AddressingMode::ZeroPage),
+    OpCode::new(0xD5, "CMP", 2, AddressingMode::ZeroPage_X),
+    OpCode::new(0xCD, "CMP", 3, AddressingMode::Absolute),
+    OpCode::new(0xDD, "CMP", 3, AddressingMode::Absolute_X),
+    OpCode::new(0xD9, "CMP", 3, AddressingMode::Absolute_Y),
+    OpCode::new(0xC1, "CMP", 2, AddressingMode::Indirect_X),
+    OpCode::new(0xD1, "CMP", 2, AddressingMode::Indirect_Y),
 
-    OpCode::new(0xE0, "CPX", 2, 2, AddressingMode::Immediate),
-    OpCode::new(0xE4, "CPX", 2, 3, AddressingMode::ZeroPage),
-    OpCode::new(0xEC, "CPX", 3, 4, AddressingMode::Absolute),
+    OpCode::new(0xE0, "CPX", 2, AddressingMode::Immediate),
+    OpCode::new(0xE4, "CPX", 2, AddressingMode::ZeroPage),
+    OpCode::new(0xEC, "CPX", 3, AddressingMode::Absolute),
 
-    OpCode::new(0xC0, "CPY", 2, 2, AddressingMode::Immediate),
-    OpCode::new(0xC4, "CPY", 2, 3, AddressingMode::ZeroPage),
-    OpCode::new(0xCC, "CPY", 3, 4, AddressingMode::Absolute),
+    OpCode::new(0xC0, "CPY", 2, AddressingMode::Immediate),
+    OpCode::new(0xC4, "CPY", 2, AddressingMode::ZeroPage),
+    OpCode::new(0xCC, "CPY", 3, AddressingMode::Absolute),
 
-    OpCode::new(0xC6, "DEC", 2, 5, AddressingMode::ZeroPage),
-    OpCode::new(0xD6, "DEC", 2, 6, AddressingMode::ZeroPage_X),
-    OpCode::new(0xCE, "DEC", 3, 6, AddressingMode::Absolute),
-    OpCode::new(0xDE, "DEC", 3, 7, AddressingMode::Absolute_X),
+    OpCode::new(0xC6, "DEC", 2, AddressingMode::ZeroPage),
+    OpCode::new(0xD6, "DEC", 2, AddressingMode::ZeroPage_X),
+    OpCode::new(0xCE, "DEC", 3, AddressingMode::Absolute),
+    OpCode::new(0xDE, "DEC", 3, AddressingMode::Absolute_X),
 
-    OpCode::new(0xCA, "DEX", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0xCA, "DEX", 1, AddressingMode::NoneAddressing),
 
-    OpCode::new(0x88, "DEY", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x88, "DEY", 1, AddressingMode::NoneAddressing),
 
-    OpCode::new(0x49, "EOR", 2, 2, AddressingMode::Immediate),
-    OpCode::new(0x45, "EOR", 2, 3, AddressingMode::ZeroPage),
-    OpCode::new(0x55, "EOR", 2, 4, AddressingMode::ZeroPage_X),
-    OpCode::new(0x4D, "EOR", 3, 4, AddressingMode::Absolute),
-    OpCode::new(0x5D, "EOR", 3, 4, AddressingMode::Absolute_X),
-    OpCode::new(0x59, "EOR", 3, 4, AddressingMode::Absolute_Y),
-    OpCode::new(0x41, "EOR", 2, 6, AddressingMode::Indirect_X),
-    OpCode::new(0x51, "EOR", 2, 5, AddressingMode::Indirect_Y),
+    OpCode::new(0x49, "EOR", 2, AddressingMode::Immediate),
+    OpCode::new(0x45, "EOR", 2, AddressingMode::ZeroPage),
+    OpCode::new(0x55, "EOR", 2, AddressingMode::ZeroPage_X),
+    OpCode::new(0x4D, "EOR", 3, AddressingMode::Absolute),
+    OpCode::new(0x5D, "EOR", 3, AddressingMode::Absolute_X),
+    OpCode::new(0x59, "EOR", 3, AddressingMode::Absolute_Y),
+    OpCode::new(0x41, "EOR", 2, AddressingMode::Indirect_X),
+    OpCode::new(0x51, "EOR", 2, AddressingMode::Indirect_Y),
 
-    OpCode::new(0xE6, "INC", 2, 5, AddressingMode::ZeroPage),
-    OpCode::new(0xF6, "INC", 2, 6, AddressingMode::ZeroPage_X),
-    OpCode::new(0xEE, "INC", 3, 6, AddressingMode::Absolute),
-    OpCode::new(0xFE, "INC", 3, 7, AddressingMode::Absolute_X),
+    OpCode::new(0xE6, "INC", 2, AddressingMode::ZeroPage),
+    OpCode::new(0xF6, "INC", 2, AddressingMode::ZeroPage_X),
+    OpCode::new(0xEE, "INC", 3, AddressingMode::Absolute),
+    OpCode::new(0xFE, "INC", 3, AddressingMode::Absolute_X),
 
-    OpCode::new(0xE8, "INX", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0xE8, "INX", 1, AddressingMode::NoneAddressing),
 
-    OpCode::new(0xC8, "INY", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0xC8, "INY", 1, AddressingMode::NoneAddressing),
 
-    OpCode::new(0x4C, "JMP", 3, 3, AddressingMode::NoneAddressing),
-    OpCode::new(0x6C, "JMP", 3, 5, AddressingMode::Indirect),
+    OpCode::new(0x4C, "JMP", 3, AddressingMode::NoneAddressing),
+    OpCode::new(0x6C, "JMP", 3, AddressingMode::Indirect),
 
-    OpCode::new(0x20, "JSR", 3, 6, AddressingMode::NoneAddressing),
+    OpCode::new(0x20, "JSR", 3, AddressingMode::NoneAddressing),
 
-    OpCode::new(0xA9, "LDA", 2, 2, AddressingMode::Immediate),
-    OpCode::new(0xA5, "LDA", 2, 3, AddressingMode::ZeroPage),
-    OpCode::new(0xB5, "LDA", 2, 4, AddressingMode::ZeroPage_X),
-    OpCode::new(0xAD, "LDA", 3, 4, AddressingMode::Absolute),
-    OpCode::new(0xBD, "LDA", 3, 4, AddressingMode::Absolute_X),
-    OpCode::new(0xB9, "LDA", 3, 4, AddressingMode::Absolute_Y),
-    OpCode::new(0xA1, "LDA", 2, 6, AddressingMode::Indirect_X),
-    OpCode::new(0xB1, "LDA", 2, 5, AddressingMode::Indirect_Y),
+    OpCode::new(0xA9, "LDA", 2, AddressingMode::Immediate),
+    OpCode::new(0xA5, "LDA", 2, AddressingMode::ZeroPage),
+    OpCode::new(0xB5, "LDA", 2, AddressingMode::ZeroPage_X),
+    OpCode::new(0xAD, "LDA", 3, AddressingMode::Absolute),
+    OpCode::new(0xBD, "LDA", 3, AddressingMode::Absolute_X),
+    OpCode::new(0xB9, "LDA", 3, AddressingMode::Absolute_Y),
+    OpCode::new(0xA1, "LDA", 2, AddressingMode::Indirect_X),
+    OpCode::new(0xB1, "LDA", 2, AddressingMode::Indirect_Y),
 
-    OpCode::new(0xA2, "LDX", 2, 2, AddressingMode::Immediate),
-    OpCode::new(0xA6, "LDX", 2, 3, AddressingMode::ZeroPage),
-    OpCode::new(0xB6, "LDX", 2, 4, AddressingMode::ZeroPage_Y),
-    OpCode::new(0xAE, "LDX", 3, 4, AddressingMode::Absolute),
-    OpCode::new(0xBE, "LDX", 3, 4, AddressingMode::Absolute_Y),
+    OpCode::new(0xA2, "LDX", 2, AddressingMode::Immediate),
+    OpCode::new(0xA6, "LDX", 2, AddressingMode::ZeroPage),
+    OpCode::new(0xB6, "LDX", 2, AddressingMode::ZeroPage_Y),
+    OpCode::new(0xAE, "LDX", 3, AddressingMode::Absolute),
+    OpCode::new(0xBE, "LDX", 3, AddressingMode::Absolute_Y),
 
-    OpCode::new(0xA0, "LDY", 2, 2, AddressingMode::Immediate),
-    OpCode::new(0xA4, "LDY", 2, 3, AddressingMode::ZeroPage),
-    OpCode::new(0xB4, "LDY", 2, 4, AddressingMode::ZeroPage_X),
-    OpCode::new(0xAC, "LDY", 3, 4, AddressingMode::Absolute),
-    OpCode::new(0xBC, "LDY", 3, 4, AddressingMode::Absolute_X),
+    OpCode::new(0xA0, "LDY", 2, AddressingMode::Immediate),
+    OpCode::new(0xA4, "LDY", 2, AddressingMode::ZeroPage),
+    OpCode::new(0xB4, "LDY", 2, AddressingMode::ZeroPage_X),
+    OpCode::new(0xAC, "LDY", 3, AddressingMode::Absolute),
+    OpCode::new(0xBC, "LDY", 3, AddressingMode::Absolute_X),
 
-    OpCode::new(0x4A, "LSR", 1, 2, AddressingMode::NoneAddressing),
-    OpCode::new(0x46, "LSR", 2, 5, AddressingMode::ZeroPage),
-    OpCode::new(0x56, "LSR", 2, 6, AddressingMode::ZeroPage_X),
-    OpCode::new(0x4E, "LSR", 3, 6, AddressingMode::Absolute),
-    OpCode::new(0x5E, "LSR", 3, 7, AddressingMode::Absolute_X),
+    OpCode::new(0x4A, "LSR", 1, AddressingMode::NoneAddressing),
+    OpCode::new(0x46, "LSR", 2, AddressingMode::ZeroPage),
+    OpCode::new(0x56, "LSR", 2, AddressingMode::ZeroPage_X),
+    OpCode::new(0x4E, "LSR", 3, AddressingMode::Absolute),
+    OpCode::new(0x5E, "LSR", 3, AddressingMode::Absolute_X),
 
-    OpCode::new(0xEA, "NOP", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0xEA, "NOP", 1, AddressingMode::NoneAddressing),
 
-    OpCode::new(0x09, "ORA", 2, 2, AddressingMode::Immediate),
-    OpCode::new(0x05, "ORA", 2, 3, AddressingMode::ZeroPage),
-    OpCode::new(0x15, "ORA", 2, 4, AddressingMode::ZeroPage_X),
-    OpCode::new(0x0D, "ORA", 3, 4, AddressingMode::Absolute),
-    OpCode::new(0x1D, "ORA", 3, 4, AddressingMode::Absolute_X),
-    OpCode::new(0x19, "ORA", 3, 4, AddressingMode::Absolute_Y),
-    OpCode::new(0x01, "ORA", 2, 6, AddressingMode::Indirect_X),
-    OpCode::new(0x11, "ORA", 2, 5, AddressingMode::Indirect_Y),
+    OpCode::new(0x09, "ORA", 2, AddressingMode::Immediate),
+    OpCode::new(0x05, "ORA", 2, AddressingMode::ZeroPage),
+    OpCode::new(0x15, "ORA", 2, AddressingMode::ZeroPage_X),
+    OpCode::new(0x0D, "ORA", 3, AddressingMode::Absolute),
+    OpCode::new(0x1D, "ORA", 3, AddressingMode::Absolute_X),
+    OpCode::new(0x19, "ORA", 3, AddressingMode::Absolute_Y),
+    OpCode::new(0x01, "ORA", 2, AddressingMode::Indirect_X),
+    OpCode::new(0x11, "ORA", 2, AddressingMode::Indirect_Y),
 
-    OpCode::new(0x48, "PHA", 1, 3, AddressingMode::NoneAddressing),
+    OpCode::new(0x48, "PHA", 1, AddressingMode::NoneAddressing),
 
-    OpCode::new(0x08, "PHP", 1, 3, AddressingMode::NoneAddressing),
+    OpCode::new(0x08, "PHP", 1, AddressingMode::NoneAddressing),
 
-    OpCode::new(0x68, "PLA", 1, 4, AddressingMode::NoneAddressing),
+    OpCode::new(0x68, "PLA", 1, AddressingMode::NoneAddressing),
 
-    OpCode::new(0x28, "PLP", 1, 4, AddressingMode::NoneAddressing),
+    OpCode::new(0x28, "PLP", 1, AddressingMode::NoneAddressing),
 
-    OpCode::new(0x2A, "ROL", 1, 2, AddressingMode::NoneAddressing),
-    OpCode::new(0x26, "ROL", 2, 5, AddressingMode::ZeroPage),
-    OpCode::new(0x36, "ROL", 2, 6, AddressingMode::ZeroPage_X),
-    OpCode::new(0x2E, "ROL", 3, 6, AddressingMode::Absolute),
-    OpCode::new(0x3E, "ROL", 3, 7, AddressingMode::Absolute_X),
+    OpCode::new(0x2A, "ROL", 1, AddressingMode::NoneAddressing),
+    OpCode::new(0x26, "ROL", 2, AddressingMode::ZeroPage),
+    OpCode::new(0x36, "ROL", 2, AddressingMode::ZeroPage_X),
+    OpCode::new(0x2E, "ROL", 3, AddressingMode::Absolute),
+    OpCode::new(0x3E, "ROL", 3, AddressingMode::Absolute_X),
 
-    OpCode::new(0x6A, "ROR", 1, 2, AddressingMode::NoneAddressing),
-    OpCode::new(0x66, "ROR", 2, 5, AddressingMode::ZeroPage),
-    OpCode::new(0x76, "ROR", 2, 6, AddressingMode::ZeroPage_X),
-    OpCode::new(0x6E, "ROR", 3, 6, AddressingMode::Absolute),
-    OpCode::new(0x7E, "ROR", 3, 7, AddressingMode::Absolute_X),
+    OpCode::new(0x6A, "ROR", 1, AddressingMode::NoneAddressing),
+    OpCode::new(0x66, "ROR", 2, AddressingMode::ZeroPage),
+    OpCode::new(0x76, "ROR", 2, AddressingMode::ZeroPage_X),
+    OpCode::new(0x6E, "ROR", 3, AddressingMode::Absolute),
+    OpCode::new(0x7E, "ROR", 3, AddressingMode::Absolute_X),
 
-    OpCode::new(0x40, "RTI", 1, 6, AddressingMode::NoneAddressing),
+    OpCode::new(0x40, "RTI", 1, AddressingMode::NoneAddressing),
 
-    OpCode::new(0x60, "RTS", 1, 6, AddressingMode::NoneAddressing),
+    OpCode::new(0x60, "RTS", 1, AddressingMode::NoneAddressing),
 
-    OpCode::new(0xE9, "SBC", 2, 2, AddressingMode::Immediate),
-    OpCode::new(0xE5, "SBC", 2, 3, AddressingMode::ZeroPage),
-    OpCode::new(0xF5, "SBC", 2, 4, AddressingMode::ZeroPage_X),
-    OpCode::new(0xED, "SBC", 3, 4, AddressingMode::Absolute),
-    OpCode::new(0xFD, "SBC", 3, 4, AddressingMode::Absolute_X),
-    OpCode::new(0xF9, "SBC", 3, 4, AddressingMode::Absolute_Y),
-    OpCode::new(0xE1, "SBC", 2, 6, AddressingMode::Indirect_X),
-    OpCode::new(0xF1, "SBC", 2, 5, AddressingMode::Indirect_Y),
+    OpCode::new(0xE9, "SBC", 2, AddressingMode::Immediate),
+    OpCode::new(0xE5, "SBC", 2, AddressingMode::ZeroPage),
+    OpCode::new(0xF5, "SBC", 2, AddressingMode::ZeroPage_X),
+    OpCode::new(0xED, "SBC", 3, AddressingMode::Absolute),
+    OpCode::new(0xFD, "SBC", 3, AddressingMode::Absolute_X),
+    OpCode::new(0xF9, "SBC", 3, AddressingMode::Absolute_Y),
+    OpCode::new(0xE1, "SBC", 2, AddressingMode::Indirect_X),
+    OpCode::new(0xF1, "SBC", 2, AddressingMode::Indirect_Y),
 
-    OpCode::new(0x38, "SEC", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x38, "SEC", 1, AddressingMode::NoneAddressing),
 
-    OpCode::new(0xF8, "SED", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0xF8, "SED", 1, AddressingMode::NoneAddressing),
 
-    OpCode::new(0x78, "SEI", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x78, "SEI", 1, AddressingMode::NoneAddressing),
 
-    OpCode::new(0x85, "STA", 2, 3, AddressingMode::ZeroPage),
-    OpCode::new(0x95, "STA", 2, 4, AddressingMode::ZeroPage_X),
-    OpCode::new(0x8D, "STA", 3, 4, AddressingMode::Absolute),
-    OpCode::new(0x9D, "STA", 3, 4, AddressingMode::Absolute_X),
-    OpCode::new(0x99, "STA", 3, 4, AddressingMode::Absolute_Y),
-    OpCode::new(0x81, "STA", 2, 6, AddressingMode::Indirect_X),
-    OpCode::new(0x91, "STA", 2, 5, AddressingMode::Indirect_Y),
+    OpCode::new(0x85, "STA", 2, AddressingMode::ZeroPage),
+    OpCode::new(0x95, "STA", 2, AddressingMode::ZeroPage_X),
+    OpCode::new(0x8D, "STA", 3, AddressingMode::Absolute),
+    OpCode::new(0x9D, "STA", 3, AddressingMode::Absolute_X),
+    OpCode::new(0x99, "STA", 3, AddressingMode::Absolute_Y),
+    OpCode::new(0x81, "STA", 2, AddressingMode::Indirect_X),
+    OpCode::new(0x91, "STA", 2, AddressingMode::Indirect_Y),
 
-    OpCode::new(0x86, "STX", 2, 3, AddressingMode::ZeroPage),
-    OpCode::new(0x96, "STX", 2, 4, AddressingMode::ZeroPage_Y),
-    OpCode::new(0x8E, "STX", 3, 4, AddressingMode::Absolute),
+    OpCode::new(0x86, "STX", 2, AddressingMode::ZeroPage),
+    OpCode::new(0x96, "STX", 2, AddressingMode::ZeroPage_Y),
+    OpCode::new(0x8E, "STX", 3, AddressingMode::Absolute),
 
-    OpCode::new(0x84, "STY", 2, 3, AddressingMode::ZeroPage),
-    OpCode::new(0x94, "STY", 2, 4, AddressingMode::ZeroPage_X),
-    OpCode::new(0x8C, "STY", 3, 4, AddressingMode::Absolute),
+    OpCode::new(0x84, "STY", 2, AddressingMode::ZeroPage),
+    OpCode::new(0x94, "STY", 2, AddressingMode::ZeroPage_X),
+    OpCode::new(0x8C, "STY", 3, AddressingMode::Absolute),
 
-    OpCode::new(0xAA, "TAX", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0xAA, "TAX", 1, AddressingMode::NoneAddressing),
 
-    OpCode::new(0xA8, "TAY", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0xA8, "TAY", 1, AddressingMode::NoneAddressing),
 
-    OpCode::new(0xBA, "TSX", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0xBA, "TSX", 1, AddressingMode::NoneAddressing),
 
-    OpCode::new(0x8A, "TXA", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x8A, "TXA", 1, AddressingMode::NoneAddressing),
 
-    OpCode::new(0x9A, "TXS", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x9A, "TXS", 1, AddressingMode::NoneAddressing),
 
-    OpCode::new(0x98, "TYA", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x98, "TYA", 1, AddressingMode::NoneAddressing),
 
     /* Unofficial Opcodes */
-    OpCode::new(0x4b, "*ALR", 2,2, AddressingMode::Immediate),
-
-    OpCode::new(0x0b, "*ANC", 2,2, AddressingMode::Immediate),
-    OpCode::new(0x2b, "*ANC", 2,2, AddressingMode::Immediate),
-
-    OpCode::new(0x6B, "*ARR", 2,2, AddressingMode::Immediate),
-
-    OpCode::new(0xCB, "*AXS", 2,2, AddressingMode::Immediate),
-
-    OpCode::new(0xa7, "*LAX", 2, 3, AddressingMode::ZeroPage),
-    OpCode::new(0xb7, "*LAX", 2, 4, AddressingMode::ZeroPage_Y),
-    OpCode::new(0xaf, "*LAX", 3, 4, AddressingMode::Absolute),
-    OpCode::new(0xbf, "*LAX", 3, 4, AddressingMode::Absolute_Y),
-    OpCode::new(0xa3, "*LAX", 2, 6, AddressingMode::Indirect_X),
-    OpCode::new(0xb3, "*LAX", 2, 5, AddressingMode::Indirect_Y),
-
-    OpCode::new(0x87, "*SAX", 2, 3, AddressingMode::ZeroPage),
-    OpCode::new(0x97, "*SAX", 2, 4, AddressingMode::ZeroPage_Y),
-    OpCode::new(0x8f, "*SAX", 3, 4, AddressingMode::Absolute),
-    OpCode::new(0x83, "*SAX", 2, 6, AddressingMode::Indirect_X),
-
-    OpCode::new(0xc7, "*DCP", 2, 5, AddressingMode::ZeroPage),
-    OpCode::new(0xd7, "*DCP", 2, 6, AddressingMode::ZeroPage_X),
-    OpCode::new(0xCF, "*DCP", 3, 6, AddressingMode::Absolute),
-    OpCode::new(0xdF, "*DCP", 3, 7, AddressingMode::Absolute_X),
-    OpCode::new(0xdb, "*DCP", 3, 7, AddressingMode::Absolute_Y),
-    OpCode::new(0xd3, "*DCP", 2, 8, AddressingMode::Indirect_Y),
-    OpCode::new(0xc3, "*DCP", 2, 8, AddressingMode::Indirect_X),
-
-    OpCode::new(0xe7, "*ISB", 2,5, AddressingMode::ZeroPage),
-    OpCode::new(0xf7, "*ISB", 2,6, AddressingMode::ZeroPage_X),
-    OpCode::new(0xef, "*ISB", 3,6, AddressingMode::Absolute),
-    OpCode::new(0xff, "*ISB", 3,7, AddressingMode::Absolute_X),
-    OpCode::new(0xfb, "*ISB", 3,7, AddressingMode::Absolute_Y),
-    OpCode::new(0xe3, "*ISB", 2,8, AddressingMode::Indirect_X),
-    OpCode::new(0xf3, "*ISB", 2,8, AddressingMode::Indirect_Y),
-
-    OpCode::new(0x27, "*RLA", 2, 5, AddressingMode::ZeroPage),
-    OpCode::new(0x37, "*RLA", 2, 6, AddressingMode::ZeroPage_X),
-    OpCode::new(0x2F, "*RLA", 3, 6, AddressingMode::Absolute),
-    OpCode::new(0x3F, "*RLA", 3, 7, AddressingMode::Absolute_X),
-    OpCode::new(0x3b, "*RLA", 3, 7, AddressingMode::Absolute_Y),
-    OpCode::new(0x33, "*RLA", 2, 8, AddressingMode::Indirect_Y),
-    OpCode::new(0x23, "*RLA", 2, 8, AddressingMode::Indirect_X),
-
-    OpCode::new(0x67, "*RRA", 2, 5, AddressingMode::ZeroPage),
-    OpCode::new(0x77, "*RRA", 2, 6, AddressingMode::ZeroPage_X),
-    OpCode::new(0x6f, "*RRA", 3, 6, AddressingMode::Absolute),
-    OpCode::new(0x7f, "*RRA", 3, 7, AddressingMode::Absolute_X),
-    OpCode::new(0x7b, "*RRA", 3, 7, AddressingMode::Absolute_Y),
-    OpCode::new(0x63, "*RRA", 2, 8, AddressingMode::Indirect_X),
-    OpCode::new(0x73, "*RRA", 2, 8, AddressingMode::Indirect_Y),
-
-    OpCode::new(0x07, "*SLO", 2, 5, AddressingMode::ZeroPage),
-    OpCode::new(0x17, "*SLO", 2, 6, AddressingMode::ZeroPage_X),
-    OpCode::new(0x0F, "*SLO", 3, 6, AddressingMode::Absolute),
-    OpCode::new(0x1f, "*SLO", 3, 7, AddressingMode::Absolute_X),
-    OpCode::new(0x1b, "*SLO", 3, 7, AddressingMode::Absolute_Y),
-    OpCode::new(0x03, "*SLO", 2, 8, AddressingMode::Indirect_X),
-    OpCode::new(0x13, "*SLO", 2, 8, AddressingMode::Indirect_Y),
-
-    OpCode::new(0x47, "*SRE", 2, 5, AddressingMode::ZeroPage),
-    OpCode::new(0x57, "*SRE", 2, 6, AddressingMode::ZeroPage_X),
-    OpCode::new(0x4F, "*SRE", 3, 6, AddressingMode::Absolute),
-    OpCode::new(0x5f, "*SRE", 3, 7, AddressingMode::Absolute_X),
-    OpCode::new(0x5b, "*SRE", 3, 7, AddressingMode::Absolute_Y),
-    OpCode::new(0x43, "*SRE", 2, 8, AddressingMode::Indirect_X),
-    OpCode::new(0x53, "*SRE", 2, 8, AddressingMode::Indirect_Y),
-
-    OpCode::new(0xeb, "*SBC", 2,2, AddressingMode::Immediate),
-
-
-    OpCode::new(0x80, "*NOP", 2,2, AddressingMode::Immediate),
-    OpCode::new(0x82, "*NOP", 2,2, AddressingMode::Immediate),
-    OpCode::new(0x89, "*NOP", 2,2, AddressingMode::Immediate),
-    OpCode::new(0xc2, "*NOP", 2,2, AddressingMode::Immediate),
-    OpCode::new(0xe2, "*NOP", 2,2, AddressingMode::Immediate),
-
-    OpCode::new(0x04, "*NOP", 2,3, AddressingMode::ZeroPage),
-    OpCode::new(0x44, "*NOP", 2,3, AddressingMode::ZeroPage),
-    OpCode::new(0x64, "*NOP", 2,3, AddressingMode::ZeroPage),
-    OpCode::new(0x14, "*NOP", 2, 4, AddressingMode::ZeroPage_X),
-    OpCode::new(0x34, "*NOP", 2, 4, AddressingMode::ZeroPage_X),
-    OpCode::new(0x54, "*NOP", 2, 4, AddressingMode::ZeroPage_X),
-    OpCode::new(0x74, "*NOP", 2, 4, AddressingMode::ZeroPage_X),
-    OpCode::new(0xd4, "*NOP", 2, 4, AddressingMode::ZeroPage_X),
-    OpCode::new(0xf4, "*NOP", 2, 4, AddressingMode::ZeroPage_X),
-    OpCode::new(0x0c, "*NOP", 3, 4, AddressingMode::Absolute),
-    OpCode::new(0x1c, "*NOP", 3, 4 /*or 5*/, AddressingMode::Absolute_X),
-    OpCode::new(0x3c, "*NOP", 3, 4 /*or 5*/, AddressingMode::Absolute_X),
-    OpCode::new(0x5c, "*NOP", 3, 4 /*or 5*/, AddressingMode::Absolute_X),
-    OpCode::new(0x7c, "*NOP", 3, 4 /*or 5*/, AddressingMode::Absolute_X),
-    OpCode::new(0xdc, "*NOP", 3, 4 /* or 5*/, AddressingMode::Absolute_X),
-    OpCode::new(0xfc, "*NOP", 3, 4 /* or 5*/, AddressingMode::Absolute_X),
-
-    OpCode::new(0x02, "*NOP", 1,2, AddressingMode::NoneAddressing),
-    OpCode::new(0x12, "*NOP", 1,2, AddressingMode::NoneAddressing),
-    OpCode::new(0x22, "*NOP", 1,2, AddressingMode::NoneAddressing),
-    OpCode::new(0x32, "*NOP", 1,2, AddressingMode::NoneAddressing),
-    OpCode::new(0x42, "*NOP", 1,2, AddressingMode::NoneAddressing),
-    OpCode::new(0x52, "*NOP", 1,2, AddressingMode::NoneAddressing),
-    OpCode::new(0x62, "*NOP", 1,2, AddressingMode::NoneAddressing),
-    OpCode::new(0x72, "*NOP", 1,2, AddressingMode::NoneAddressing),
-    OpCode::new(0x92, "*NOP", 1,2, AddressingMode::NoneAddressing),
-    OpCode::new(0xb2, "*NOP", 1,2, AddressingMode::NoneAddressing),
-    OpCode::new(0xd2, "*NOP", 1,2, AddressingMode::NoneAddressing),
-    OpCode::new(0xf2, "*NOP", 1,2, AddressingMode::NoneAddressing),
-
-    OpCode::new(0x1a, "*NOP", 1,2, AddressingMode::NoneAddressing),
-    OpCode::new(0x3a, "*NOP", 1,2, AddressingMode::NoneAddressing),
-    OpCode::new(0x5a, "*NOP", 1,2, AddressingMode::NoneAddressing),
-    OpCode::new(0x7a, "*NOP", 1,2, AddressingMode::NoneAddressing),
-    OpCode::new(0xda, "*NOP", 1,2, AddressingMode::NoneAddressing),
-    // OpCode::new(0xea, "NOP", 1,2, AddressingMode::NoneAddressing),
-    OpCode::new(0xfa, "*NOP", 1,2, AddressingMode::NoneAddressing),
-
-    OpCode::new(0xab, "*LXA", 2, 3, AddressingMode::Immediate), //todo: highly unstable and not used
+    OpCode::new(0x4b, "*ALR", 2, AddressingMode::Immediate),
+
+    OpCode::new(0x0b, "*ANC", 2, AddressingMode::Immediate),
+    OpCode::new(0x2b, "*ANC", 2, AddressingMode::Immediate),
+
+    OpCode::new(0x6B, "*ARR", 2, AddressingMode::Immediate),
+
+    OpCode::new(0xCB, "*AXS", 2, AddressingMode::Immediate),
+
+    OpCode::new(0xa7, "*LAX", 2, AddressingMode::ZeroPage),
+    OpCode::new(0xb7, "*LAX", 2, AddressingMode::ZeroPage_Y),
+    OpCode::new(0xaf, "*LAX", 3, AddressingMode::Absolute),
+    OpCode::new(0xbf, "*LAX", 3, AddressingMode::Absolute_Y),
+    OpCode::new(0xa3, "*LAX", 2, AddressingMode::Indirect_X),
+    OpCode::new(0xb3, "*LAX", 2, AddressingMode::Indirect_Y),
+
+    OpCode::new(0x87, "*SAX", 2, AddressingMode::ZeroPage),
+    OpCode::new(0x97, "*SAX", 2, AddressingMode::ZeroPage_Y),
+    OpCode::new(0x8f, "*SAX", 3, AddressingMode::Absolute),
+    OpCode::new(0x83, "*SAX", 2, AddressingMode::Indirect_X),
+
+    OpCode::new(0xc7, "*DCP", 2, AddressingMode::ZeroPage),
+    OpCode::new(0xd7, "*DCP", 2, AddressingMode::ZeroPage_X),
+    OpCode::new(0xCF, "*DCP", 3, AddressingMode::Absolute),
+    OpCode::new(0xdF, "*DCP", 3, AddressingMode::Absolute_X),
+    OpCode::new(0xdb, "*DCP", 3, AddressingMode::Absolute_Y),
+    OpCode::new(0xd3, "*DCP", 2, AddressingMode::Indirect_Y),
+    OpCode::new(0xc3, "*DCP", 2, AddressingMode::Indirect_X),
+
+    OpCode::new(0xe7, "*ISB", 2, AddressingMode::ZeroPage),
+    OpCode::new(0xf7, "*ISB", 2, AddressingMode::ZeroPage_X),
+    OpCode::new(0xef, "*ISB", 3, AddressingMode::Absolute),
+    OpCode::new(0xff, "*ISB", 3, AddressingMode::Absolute_X),
+    OpCode::new(0xfb, "*ISB", 3, AddressingMode::Absolute_Y),
+    OpCode::new(0xe3, "*ISB", 2, AddressingMode::Indirect_X),
+    OpCode::new(0xf3, "*ISB", 2, AddressingMode::Indirect_Y),
+
+    OpCode::new(0x27, "*RLA", 2, AddressingMode::ZeroPage),
+    OpCode::new(0x37, "*RLA", 2, AddressingMode::ZeroPage_X),
+    OpCode::new(0x2F, "*RLA", 3, AddressingMode::Absolute),
+    OpCode::new(0x3F, "*RLA", 3, AddressingMode::Absolute_X),
+    OpCode::new(0x3b, "*RLA", 3, AddressingMode::Absolute_Y),
+    OpCode::new(0x33, "*RLA", 2, AddressingMode::Indirect_Y),
+    OpCode::new(0x23, "*RLA", 2, AddressingMode::Indirect_X),
+
+    OpCode::new(0x67, "*RRA", 2, AddressingMode::ZeroPage),
+    OpCode::new(0x77, "*RRA", 2, AddressingMode::ZeroPage_X),
+    OpCode::new(0x6f, "*RRA", 3, AddressingMode::Absolute),
+    OpCode::new(0x7f, "*RRA", 3, AddressingMode::Absolute_X),
+    OpCode::new(0x7b, "*RRA", 3, AddressingMode::Absolute_Y),
+    OpCode::new(0x63, "*RRA", 2, AddressingMode::Indirect_X),
+    OpCode::new(0x73, "*RRA", 2, AddressingMode::Indirect_Y),
+
+    OpCode::new(0x07, "*SLO", 2, AddressingMode::ZeroPage),
+    OpCode::new(0x17, "*SLO", 2, AddressingMode::ZeroPage_X),
+    OpCode::new(0x0F, "*SLO", 3, AddressingMode::Absolute),
+    OpCode::new(0x1f, "*SLO", 3, AddressingMode::Absolute_X),
+    OpCode::new(0x1b, "*SLO", 3, AddressingMode::Absolute_Y),
+    OpCode::new(0x03, "*SLO", 2, AddressingMode::Indirect_X),
+    OpCode::new(0x13, "*SLO", 2, AddressingMode::Indirect_Y),
+
+    OpCode::new(0x47, "*SRE", 2, AddressingMode::ZeroPage),
+    OpCode::new(0x57, "*SRE", 2, AddressingMode::ZeroPage_X),
+    OpCode::new(0x4F, "*SRE", 3, AddressingMode::Absolute),
+    OpCode::new(0x5f, "*SRE", 3, AddressingMode::Absolute_X),
+    OpCode::new(0x5b, "*SRE", 3, AddressingMode::Absolute_Y),
+    OpCode::new(0x43, "*SRE", 2, AddressingMode::Indirect_X),
+    OpCode::new(0x53, "*SRE", 2, AddressingMode::Indirect_Y),
+
+    OpCode::new(0xeb, "*SBC", 2, AddressingMode::Immediate),
+
+
+    OpCode::new(0x80, "*NOP", 2, AddressingMode::Immediate),
+    OpCode::new(0x82, "*NOP", 2, AddressingMode::Immediate),
+    OpCode::new(0x89, "*NOP", 2, AddressingMode::Immediate),
+    OpCode::new(0xc2, "*NOP", 2, AddressingMode::Immediate),
+    OpCode::new(0xe2, "*NOP", 2, AddressingMode::Immediate),
+
+    OpCode::new(0x04, "*NOP", 2, AddressingMode::ZeroPage),
+    OpCode::new(0x44, "*NOP", 2, AddressingMode::ZeroPage),
+    OpCode::new(0x64, "*NOP", 2, AddressingMode::ZeroPage),
+    OpCode::new(0x14, "*NOP", 2, AddressingMode::ZeroPage_X),
+    OpCode::new(0x34, "*NOP", 2, AddressingMode::ZeroPage_X),
+    OpCode::new(0x54, "*NOP", 2, AddressingMode::ZeroPage_X),
+    OpCode::new(0x74, "*NOP", 2, AddressingMode::ZeroPage_X),
+    OpCode::new(0xd4, "*NOP", 2, AddressingMode::ZeroPage_X),
+    OpCode::new(0xf4, "*NOP", 2, AddressingMode::ZeroPage_X),
+    OpCode::new(0x0c, "*NOP", 3, AddressingMode::Absolute),
+    OpCode::new(0x1c, "*NOP", 3, AddressingMode::Absolute_X),
+    OpCode::new(0x3c, "*NOP", 3, AddressingMode::Absolute_X),
+    OpCode::new(0x5c, "*NOP", 3, AddressingMode::Absolute_X),
+    OpCode::new(0x7c, "*NOP", 3, AddressingMode::Absolute_X),
+    OpCode::new(0xdc, "*NOP", 3, AddressingMode::Absolute_X),
+    OpCode::new(0xfc, "*NOP", 3, AddressingMode::Absolute_X),
+
+    OpCode::new(0x02, "*JAM", 1, AddressingMode::NoneAddressing),
+    OpCode::new(0x12, "*JAM", 1, AddressingMode::NoneAddressing),
+    OpCode::new(0x22, "*JAM", 1, AddressingMode::NoneAddressing),
+    OpCode::new(0x32, "*JAM", 1, AddressingMode::NoneAddressing),
+    OpCode::new(0x42, "*JAM", 1, AddressingMode::NoneAddressing),
+    OpCode::new(0x52, "*JAM", 1, AddressingMode::NoneAddressing),
+    OpCode::new(0x62, "*JAM", 1, AddressingMode::NoneAddressing),
+    OpCode::new(0x72, "*JAM", 1, AddressingMode::NoneAddressing),
+    OpCode::new(0x92, "*JAM", 1, AddressingMode::NoneAddressing),
+    OpCode::new(0xb2, "*JAM", 1, AddressingMode::NoneAddressing),
+    OpCode::new(0xd2, "*JAM", 1, AddressingMode::NoneAddressing),
+    OpCode::new(0xf2, "*JAM", 1, AddressingMode::NoneAddressing),
+
+    OpCode::new(0x1a, "*NOP", 1, AddressingMode::NoneAddressing),
+    OpCode::new(0x3a, "*NOP", 1, AddressingMode::NoneAddressing),
+    OpCode::new(0x5a, "*NOP", 1, AddressingMode::NoneAddressing),
+    OpCode::new(0x7a, "*NOP", 1, AddressingMode::NoneAddressing),
+    OpCode::new(0xda, "*NOP", 1, AddressingMode::NoneAddressing),
+    // OpCode::new(0xea, "NOP", 1, AddressingMode::NoneAddressing),
+    OpCode::new(0xfa, "*NOP", 1, AddressingMode::NoneAddressing),
+
+    OpCode::new(0xab, "*LXA", 2, AddressingMode::Immediate), //todo: highly unstable and not used
     //http://visual6502.org/wiki/index.php?title=6502_Opcode_8B_%28XAA,_ANE%29
-    OpCode::new(0x8b, "*XAA", 2, 3, AddressingMode::Immediate), //todo: highly unstable and not used
-    OpCode::new(0xbb, "*LAS", 3, 2, AddressingMode::Absolute_Y), //todo: highly unstable and not used
-    OpCode::new(0x9b, "*TAS", 3, 2, AddressingMode::Absolute_Y), //todo: highly unstable and not used
-    OpCode::new(0x93, "*AHX", 2, /* guess */ 8, AddressingMode::Indirect_Y), //todo: highly unstable and not used
-    OpCode::new(0x9f, "*AHX", 3, /* guess */ 4/* or 5*/, AddressingMode::Absolute_Y), //todo: highly unstable and not used
-    OpCode::new(0x9e, "*SHX", 3, /* guess */ 4/* or 5*/, AddressingMode::Absolute_Y), //todo: highly unstable and not used
-    OpCode::new(0x9c, "*SHY", 3, /* guess */ 4/* or 5*/, AddressingMode::Absolute_X), //todo: highly unstable and not used
+    OpCode::new(0x8b, "*XAA", 2, AddressingMode::Immediate), //todo: highly unstable and not used
+    OpCode::new(0xbb, "*LAS", 3, AddressingMode::Absolute_Y), //todo: highly unstable and not used
+    OpCode::new(0x9b, "*TAS", 3, AddressingMode::Absolute_Y), //todo: highly unstable and not used
+    OpCode::new(0x93, "*AHX", 2, AddressingMode::Indirect_Y), //todo: highly unstable and not used
+    OpCode::new(0x9f, "*AHX", 3, AddressingMode::Absolute_Y), //todo: highly unstable and not used
+    OpCode::new(0x9e, "*SHX", 3, AddressingMode::Absolute_Y), //todo: highly unstable and not used
+    OpCode::new(0x9c, "*SHY", 3, AddressingMode::Absolute_X), //todo: highly unstable and not used
 ]});
 
-pub static OPCODE_MAP: Lazy<HashMap<u8, &'static OpCode>> = Lazy::new(|| {
-    let mut map: HashMap<u8, &OpCode> = HashMap::new();
+// Indexed by opcode byte, so the CPU can dispatch without hashing or comparing names.
+pub static OPCODE_TABLE: Lazy<[&'static OpCode; 256]> = Lazy::new(|| {
+    let mut table: [Option<&OpCode>; 256] = [None; 256];
     for op in CPU_OPS_CODES.iter() {
-        map.insert(op.code, op);
+        table[op.code as usize] = Some(op);
     }
-    map
-});
\ No newline at end of file
+    table.map(|op| op.expect("every opcode byte has an entry"))
+});
@@ -1,9 +1,14 @@
 mod registers;
+mod render;
 
-use self::registers::{
-    AddrRegister, ControlRegister, MaskRegister, ScrollRegister, StatusRegister,
-};
+use self::registers::{ControlRegister, MaskRegister, StatusRegister, VramAddrRegister};
 use crate::cartridge::Mirroring;
+use crate::mapper::SharedMapper;
+use crate::renderer::frame::Frame;
+use crate::savestate::{Snapshot, StateReader, StateWriter};
+
+// Frames a bit of the I/O latch holds its charge after being driven, roughly 600ms.
+const OPEN_BUS_DECAY_FRAMES: u8 = 36;
 
 #[derive(Copy, Clone)]
 pub enum TileId {
@@ -64,6 +69,18 @@ impl Sprite {
             x: oam[3],
         }
     }
+
+    pub fn oam_bytes(&self) -> [u8; 4] {
+        let tile = match self.tile_id {
+            TileId::Normal { id } => id,
+            TileId::Large { bank, id } => id | (bank == 0x1000) as u8,
+        };
+        let attr = self.attr.palette
+            | self.attr.priority << 5
+            | (self.attr.is_flip_horizonal as u8) << 6
+            | (self.attr.is_flip_vertical as u8) << 7;
+        [self.y, tile, attr, self.x]
+    }
 }
 
 enum LineStatus {
@@ -89,9 +106,7 @@ impl LineStatus {
 }
 
 pub struct NesPPU {
-    pub chr_rom: Vec<u8>,
-    pub is_chr_ram: bool,
-    pub mirroring: Mirroring,
+    pub mapper: SharedMapper,
     pub palette_table: [u8; 32],
 
     pub ctrl: ControlRegister,
@@ -99,17 +114,35 @@ pub struct NesPPU {
     pub status: StatusRegister,
     pub oam_addr: u8,
     pub oam_data: [u8; 256],
-    pub scroll: ScrollRegister,
-    pub addr: AddrRegister,
-    pub vram: [u8; 2048],
+    pub vram_addr: VramAddrRegister,
+    // nametable RAM, sized by the cartridge
+    pub vram: Vec<u8>,
 
     internal_data_buf: u8,
+    // The data bus between the CPU and the PPU registers keeps the last value
+    // driven on it, bit by bit, until the charge leaks away.
+    io_latch: u8,
+    io_latch_decay: [u8; 8],
 
     scanline: u16,
     cycles: usize,
     pub nmi_interrupt: Option<u8>,
     secondary_oam_data: [Option<Sprite>; 8],
     sprite_zero_flags: [bool; 8],
+
+    // Background and sprite pipelines, see render.rs.
+    bg_next_tile: u8,
+    bg_next_attr: u8,
+    bg_next_lo: u8,
+    bg_next_hi: u8,
+    bg_pattern_lo: u16,
+    bg_pattern_hi: u16,
+    bg_attr_lo: u16,
+    bg_attr_hi: u16,
+    sprite_patterns: [(u8, u8); 8],
+
+    // The picture as it is being drawn, complete once vblank starts.
+    pub frame: Frame,
 }
 
 pub trait PPU {
@@ -118,67 +151,157 @@ pub trait PPU {
     fn read_status(&mut self) -> u8;
     fn write_to_oam_addr(&mut self, value: u8);
     fn write_to_oam_data(&mut self, value: u8);
-    fn read_oam_data(&self) -> u8;
+    fn read_oam_data(&mut self) -> u8;
     fn write_to_scroll(&mut self, value: u8);
     fn write_to_ppu_addr(&mut self, value: u8);
     fn write_to_data(&mut self, value: u8);
     fn read_data(&mut self) -> u8;
     fn write_oam_dma(&mut self, value: &[u8; 256]);
+    fn write_to_status(&mut self, value: u8);
+    fn read_open_bus(&self) -> u8;
 }
 
 impl NesPPU {
-    pub fn new(chr_rom: Vec<u8>, is_chr_ram: bool, mirroring: Mirroring) -> Self {
+    pub fn new(mapper: SharedMapper) -> Self {
+        let vram_size = mapper.borrow().nametable_ram_size();
         NesPPU {
-            chr_rom,
-            is_chr_ram,
-            mirroring,
+            mapper,
             palette_table: [0; 32],
             ctrl: ControlRegister::new(),
             mask: MaskRegister::new(),
             status: StatusRegister::new(),
             oam_addr: 0,
             oam_data: [0; 64 * 4],
-            scroll: ScrollRegister::new(),
-            addr: AddrRegister::new(),
-            vram: [0; 2048],
+            vram_addr: VramAddrRegister::new(),
+            vram: vec![0; vram_size],
             internal_data_buf: 0,
+            io_latch: 0,
+            io_latch_decay: [0; 8],
 
-            scanline: 241,
+            scanline: 0,
             cycles: 0,
             nmi_interrupt: None,
             secondary_oam_data: [None; 8],
             sprite_zero_flags: [false; 8],
+
+            bg_next_tile: 0,
+            bg_next_attr: 0,
+            bg_next_lo: 0,
+            bg_next_hi: 0,
+            bg_pattern_lo: 0,
+            bg_pattern_hi: 0,
+            bg_attr_lo: 0,
+            bg_attr_hi: 0,
+            sprite_patterns: [(0, 0); 8],
+
+            frame: Frame::new(),
         }
     }
 
-    fn increment_vram_addr(&mut self) {
-        self.addr.increment(self.ctrl.vram_addr_increment());
+    // The reset line clears the control, mask and scroll registers and the write latch.
+    // Memory, OAM, the status flags and the VRAM address are left alone.
+    pub fn reset(&mut self) {
+        self.ctrl = ControlRegister::new();
+        self.mask = MaskRegister::new();
+        self.vram_addr.reset();
+        self.internal_data_buf = 0;
+        self.nmi_interrupt = None;
+    }
+
+    pub fn mirroring(&self) -> Mirroring {
+        self.mapper.borrow().mirroring()
+    }
+
+    pub fn read_chr(&self, addr: u16) -> u8 {
+        self.mapper.borrow_mut().ppu_read(addr)
+    }
+
+    pub fn read_tile(&self, bank: u16, tile_idx: u16) -> [u8; 16] {
+        let mut mapper = self.mapper.borrow_mut();
+        let mut tile = [0; 16];
+        for (i, byte) in tile.iter_mut().enumerate() {
+            *byte = mapper.ppu_read(bank + tile_idx * 16 + i as u16);
+        }
+        tile
+    }
+
+    // Drives the bits in `mask` of the I/O latch with `value`.
+    fn refresh_io_latch(&mut self, value: u8, mask: u8) {
+        self.io_latch = (self.io_latch & !mask) | (value & mask);
+        for bit in 0..8 {
+            if mask & (1 << bit) != 0 {
+                self.io_latch_decay[bit] = OPEN_BUS_DECAY_FRAMES;
+            }
+        }
+    }
+
+    fn decay_io_latch(&mut self) {
+        for bit in 0..8 {
+            if self.io_latch_decay[bit] > 0 {
+                self.io_latch_decay[bit] -= 1;
+                if self.io_latch_decay[bit] == 0 {
+                    self.io_latch &= !(1 << bit);
+                }
+            }
+        }
     }
 
-    // Horizontal:
-    //   [ A ] [ a ]
-    //   [ B ] [ b ]
+    // While rendering, $2007 accesses bump v with both the coarse X and the Y
+    // increments instead of the usual +1/+32.
+    fn increment_vram_addr(&mut self) {
+        let is_rendering_line = matches!(LineStatus::from(self.scanline), LineStatus::Visible | LineStatus::PreRender);
+        if self.is_rendering_enabled() && is_rendering_line {
+            self.vram_addr.increment_x();
+            self.vram_addr.increment_y();
+        } else {
+            self.vram_addr.increment(self.ctrl.vram_addr_increment());
+        }
+    }
 
-    // Vertical:
-    //   [ A ] [ B ]
-    //   [ a ] [ b ]
+    // The cartridge decides how the nametables map onto VRAM, see `Mapper::nametable_addr`.
     pub fn mirror_vram_addr(&self, addr: u16) -> u16 {
         let mirrored_vram = addr & 0b10111111111111; // mirror down 0x3000-0x3eff to 0x2000 - 0x2eff
-        let vram_index = mirrored_vram - 0x2000; // to vram vector
-        let name_table = vram_index / 0x400; // to the name table index
-        match (&self.mirroring, name_table) {
-            (Mirroring::VERTICAL, 2) | (Mirroring::VERTICAL, 3) => vram_index - 0x800,
-            (Mirroring::HORIZONTAL, 2) => vram_index - 0x400,
-            (Mirroring::HORIZONTAL, 1) => vram_index - 0x400,
-            (Mirroring::HORIZONTAL, 3) => vram_index - 0x800,
-            _ => vram_index,
-        }
+        self.mapper.borrow().nametable_addr(mirrored_vram)
     }
 
-    fn is_sprite_0_hit(&self, cycle: usize) -> bool {
-        let y = self.oam_data[0] as usize;
-        let x = self.oam_data[3] as usize;
-        (y == self.scanline as usize) && x <= cycle && self.mask.show_sprites()
+    fn is_rendering_enabled(&self) -> bool {
+        self.mask.show_background() || self.mask.show_sprites()
+    }
+
+    // Pattern table address driven on the PPU bus at the current dot, if any.
+    // Only the pattern table half matters to the mappers listening on A12, so
+    // background fetches report the table base rather than the exact tile.
+    fn pattern_fetch_addr(&self) -> Option<u16> {
+        if !self.is_rendering_enabled() {
+            return None;
+        }
+        match LineStatus::from(self.scanline) {
+            LineStatus::Visible | LineStatus::PreRender => {}
+            _ => return None,
+        }
+
+        let plane = match self.cycles % 8 {
+            5 => 0,
+            7 => 8,
+            _ => return None,
+        };
+        match self.cycles {
+            1..=256 | 321..=336 => Some(self.ctrl.bknd_pattern_addr() | plane),
+            257..=320 => {
+                let slot = (self.cycles - 257) / 8;
+                let addr = match self.secondary_oam_data[slot] {
+                    Some(sprite) => match sprite.tile_id {
+                        TileId::Normal { id } => self.ctrl.sprt_pattern_addr() + id as u16 * 16,
+                        TileId::Large { bank, id } => bank + id as u16 * 16,
+                    },
+                    None if self.ctrl.sprite_size() == 8 => self.ctrl.sprt_pattern_addr() + 0xff * 16,
+                    // empty slots fetch tile $FF, which is in the $1000 table for 8x16 sprites
+                    None => 0x1000 + 0xfe * 16,
+                };
+                Some(addr | plane)
+            }
+            _ => None,
+        }
     }
 
     pub fn tick(&mut self) {
@@ -187,17 +310,10 @@ impl NesPPU {
             self.cycles = self.cycles - 341;
 
             match LineStatus::from(self.scanline) {
-                LineStatus::Visible => {
-                    if self.is_sprite_0_hit(self.cycles as usize) {
-                        self.status.set_sprite_zero_hit(true)
-                    }
-                    self.sprite_evaluation();
-                }
-                LineStatus::PostRender => {}
+                LineStatus::Visible | LineStatus::PostRender => {}
                 LineStatus::VerticalBlanking(is_first) => {
                     if is_first {
                         self.status.set_vblank_status(true);
-                        self.status.set_sprite_zero_hit(false);
                         if self.ctrl.generate_vblank_nmi() {
                             self.nmi_interrupt = Some(1);
                         }
@@ -206,104 +322,217 @@ impl NesPPU {
                 LineStatus::PreRender => {
                     self.nmi_interrupt = None;
                     self.status.set_sprite_zero_hit(false);
+                    self.status.set_sprite_overflow(false);
                     self.status.reset_vblank_status();
                 }
             }
-            self.scanline = (self.scanline + 1) % 262
+            self.scanline = (self.scanline + 1) % 262;
+            if self.scanline == 0 {
+                self.decay_io_latch();
+            }
+        }
+
+        if let Some(addr) = self.pattern_fetch_addr() {
+            self.mapper.borrow_mut().ppu_bus_address(addr);
         }
+        self.render_dot();
         self.cycles += 1;
     }
 
+    // Picks the sprites for the next line. Sprites are drawn one line below their Y.
     fn sprite_evaluation(&mut self) {
-        if !self.mask.show_sprites() {
-            return;
-        }
+        self.secondary_oam_data = [None; 8];
+        self.sprite_zero_flags = [false; 8];
         let sprite_height = u16::from(self.ctrl.sprite_size());
+        let is_on_line = |y: u8| (y as u16) <= self.scanline && (y as u16) + sprite_height > self.scanline;
+
+        let mut sprite_idx = 0;
         let mut tmp_idx = 0;
-        for sprite_idx in 0..64 {
+        while sprite_idx < 64 && tmp_idx < 8 {
             let oam_addr = sprite_idx << 2;
-            let sprite_y = u16::from(self.oam_data[oam_addr]);
-            let sprite_y_end = sprite_y + sprite_height;
-            
-            if (sprite_y < self.scanline) && (sprite_y_end >= self.scanline) {
-                if sprite_idx == 0 && self.scanline==sprite_y {
-                    self.status.set_sprite_zero_hit(true);
-                }
-                if tmp_idx >= 8 {
-                    self.status.set_sprite_overflow(true);
-                    break;
-                } else {
-                    self.secondary_oam_data[tmp_idx] = Some(Sprite::new(sprite_height == 16, &self.oam_data[oam_addr..oam_addr+4]));
-                    self.sprite_zero_flags[tmp_idx] = sprite_idx == 0;
-                    tmp_idx += 1;
-                }
+            if is_on_line(self.oam_data[oam_addr]) {
+                self.secondary_oam_data[tmp_idx] = Some(Sprite::new(sprite_height == 16, &self.oam_data[oam_addr..oam_addr+4]));
+                self.sprite_zero_flags[tmp_idx] = sprite_idx == 0;
+                tmp_idx += 1;
+            }
+            sprite_idx += 1;
+        }
+
+        // Once 8 sprites are found the hardware keeps looking for a 9th, but it
+        // steps the byte offset along with the sprite index, so it compares tile
+        // numbers, attributes and X positions as if they were Y coordinates.
+        let mut byte = 0;
+        while sprite_idx < 64 {
+            if is_on_line(self.oam_data[(sprite_idx << 2) + byte]) {
+                self.status.set_sprite_overflow(true);
+                break;
             }
+            sprite_idx += 1;
+            byte = (byte + 1) & 0b11;
         }
     }
 
     pub fn poll_nmi_interrupt(&mut self) -> Option<u8> {
         self.nmi_interrupt.take()
     }
+
+    // Scanline and dot the next tick will render, as shown in nestest.log.
+    pub fn position(&self) -> (u16, usize) {
+        if self.cycles >= 341 {
+            ((self.scanline + 1) % 262, self.cycles - 341)
+        } else {
+            (self.scanline, self.cycles)
+        }
+    }
+}
+
+impl Snapshot for NesPPU {
+    fn save_state(&self, w: &mut StateWriter) {
+        w.write_bytes(&self.palette_table);
+        w.write_u8(self.ctrl.bits());
+        w.write_u8(self.mask.bits());
+        w.write_u8(self.status.bits());
+        w.write_u8(self.oam_addr);
+        w.write_bytes(&self.oam_data);
+        self.vram_addr.save_state(w);
+        w.write_bytes(&self.vram);
+        w.write_u8(self.internal_data_buf);
+        w.write_u8(self.io_latch);
+        w.write_bytes(&self.io_latch_decay);
+        w.write_u16(self.scanline);
+        w.write_usize(self.cycles);
+        w.write_bool(self.nmi_interrupt.is_some());
+        for (sprite, is_zero) in self.secondary_oam_data.iter().zip(self.sprite_zero_flags) {
+            match sprite {
+                Some(sprite) => {
+                    w.write_bool(true);
+                    w.write_bool(matches!(sprite.tile_id, TileId::Large { .. }));
+                    w.write_bytes(&sprite.oam_bytes());
+                }
+                None => w.write_bool(false),
+            }
+            w.write_bool(is_zero);
+        }
+        w.write_u8(self.bg_next_tile);
+        w.write_u8(self.bg_next_attr);
+        w.write_u8(self.bg_next_lo);
+        w.write_u8(self.bg_next_hi);
+        for shifter in [self.bg_pattern_lo, self.bg_pattern_hi, self.bg_attr_lo, self.bg_attr_hi] {
+            w.write_u16(shifter);
+        }
+        for (lo, hi) in self.sprite_patterns {
+            w.write_u8(lo);
+            w.write_u8(hi);
+        }
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), String> {
+        r.read_into(&mut self.palette_table)?;
+        self.ctrl = ControlRegister::from_bits_truncate(r.read_u8()?);
+        self.mask = MaskRegister::from_bits_truncate(r.read_u8()?);
+        self.status = StatusRegister::from_bits_truncate(r.read_u8()?);
+        self.oam_addr = r.read_u8()?;
+        r.read_into(&mut self.oam_data)?;
+        self.vram_addr.load_state(r)?;
+        r.read_into(&mut self.vram)?;
+        self.internal_data_buf = r.read_u8()?;
+        self.io_latch = r.read_u8()?;
+        r.read_into(&mut self.io_latch_decay)?;
+        self.scanline = r.read_u16()?;
+        self.cycles = r.read_usize()?;
+        self.nmi_interrupt = if r.read_bool()? { Some(1) } else { None };
+        for i in 0..self.secondary_oam_data.len() {
+            self.secondary_oam_data[i] = if r.read_bool()? {
+                let is_large = r.read_bool()?;
+                let mut oam = [0; 4];
+                r.read_into(&mut oam)?;
+                Some(Sprite::new(is_large, &oam))
+            } else {
+                None
+            };
+            self.sprite_zero_flags[i] = r.read_bool()?;
+        }
+        self.bg_next_tile = r.read_u8()?;
+        self.bg_next_attr = r.read_u8()?;
+        self.bg_next_lo = r.read_u8()?;
+        self.bg_next_hi = r.read_u8()?;
+        self.bg_pattern_lo = r.read_u16()?;
+        self.bg_pattern_hi = r.read_u16()?;
+        self.bg_attr_lo = r.read_u16()?;
+        self.bg_attr_hi = r.read_u16()?;
+        for pattern in self.sprite_patterns.iter_mut() {
+            *pattern = (r.read_u8()?, r.read_u8()?);
+        }
+        Ok(())
+    }
 }
 
 impl PPU for NesPPU {
     fn write_to_ctrl(&mut self, value: u8) {
+        self.refresh_io_latch(value, 0xff);
         let before_nmi_status = self.ctrl.generate_vblank_nmi();
         self.ctrl.update(value);
+        self.vram_addr.write_ctrl(value);
         if !before_nmi_status && self.ctrl.generate_vblank_nmi() && self.status.is_in_vblank() {
             self.nmi_interrupt = Some(1);
         }
     }
 
     fn write_to_mask(&mut self, value: u8) {
+        self.refresh_io_latch(value, 0xff);
         self.mask.update(value);
     }
 
+    // Only the top three bits come from the status register, the rest is open bus.
     fn read_status(&mut self) -> u8 {
-        let data = self.status.snapshot();
+        self.refresh_io_latch(self.status.snapshot(), 0b1110_0000);
+        let data = self.io_latch;
         self.status.reset_vblank_status();
-        self.addr.reset_latch();
-        self.scroll.reset_latch();
+        self.vram_addr.reset_latch();
         data
     }
 
     fn write_to_oam_addr(&mut self, value: u8) {
+        self.refresh_io_latch(value, 0xff);
         self.oam_addr = value;
     }
 
     fn write_to_oam_data(&mut self, value: u8) {
+        self.refresh_io_latch(value, 0xff);
         self.oam_data[self.oam_addr as usize] = value;
         self.oam_addr = self.oam_addr.wrapping_add(1);
     }
 
-    fn read_oam_data(&self) -> u8 {
-        self.oam_data[self.oam_addr as usize]
+    fn read_oam_data(&mut self) -> u8 {
+        let data = self.oam_data[self.oam_addr as usize];
+        self.refresh_io_latch(data, 0xff);
+        data
     }
 
     fn write_to_scroll(&mut self, value: u8) {
-        self.scroll.write(value);
+        self.refresh_io_latch(value, 0xff);
+        self.vram_addr.write_scroll(value);
     }
 
     fn write_to_ppu_addr(&mut self, value: u8) {
-        self.addr.update(value);
+        self.refresh_io_latch(value, 0xff);
+        self.vram_addr.write_addr(value);
     }
 
     fn write_to_data(&mut self, value: u8) {
-        let addr = self.addr.get();
+        self.refresh_io_latch(value, 0xff);
+        let addr = self.vram_addr.get();
+        self.mapper.borrow_mut().ppu_bus_address(addr);
         match addr {
-            0..=0x1fff => {
-                if self.is_chr_ram {
-                    self.chr_rom[addr as usize] = value;
-                } else {
-                    println!("Attempt to write to chr rom space 0x{:<04x}", addr)
-                }
-            },
+            0..=0x1fff => self.mapper.borrow_mut().ppu_write(addr, value),
             0x2000..=0x2fff => {
-                self.vram[self.mirror_vram_addr(addr) as usize] = value;
+                let index = self.mirror_vram_addr(addr) as usize;
+                self.vram[index] = value;
             }
             0x3000..=0x3eff => {
                 let addr_mirror = addr - 0x1000;
-                self.vram[self.mirror_vram_addr(addr_mirror) as usize] = value;
+                let index = self.mirror_vram_addr(addr_mirror) as usize;
+                self.vram[index] = value;
             }
             0x3f10 | 0x3f14 | 0x3f18 | 0x3f1c => {
                 let add_mirror = addr - 0x10;
@@ -318,13 +547,14 @@ impl PPU for NesPPU {
     }
 
     fn read_data(&mut self) -> u8 {
-        let addr = self.addr.get();
+        let addr = self.vram_addr.get();
+        self.mapper.borrow_mut().ppu_bus_address(addr);
         self.increment_vram_addr();
 
-        match addr {
+        let data = match addr {
             0..=0x1fff => {
                 let result = self.internal_data_buf;
-                self.internal_data_buf = self.chr_rom[addr as usize];
+                self.internal_data_buf = self.read_chr(addr);
                 result
             }
             0x2000..=0x2fff => {
@@ -352,7 +582,24 @@ impl PPU for NesPPU {
 
             0x3f00..=0x3fff => self.palette_table[((addr - 0x3f00) as usize)%32],
             _ => panic!("unexpected access to mirrored space {}", addr),
+        };
+
+        // palette entries are 6 bits wide, the top two come from the latch
+        if addr >= 0x3f00 {
+            self.refresh_io_latch(data, 0b0011_1111);
+        } else {
+            self.refresh_io_latch(data, 0xff);
         }
+        self.io_latch
+    }
+
+    // The status register can't be written, but the value still lands on the latch.
+    fn write_to_status(&mut self, value: u8) {
+        self.refresh_io_latch(value, 0xff);
+    }
+
+    fn read_open_bus(&self) -> u8 {
+        self.io_latch
     }
 
     fn write_oam_dma(&mut self, value: &[u8; 256]) {
@@ -362,3 +609,70 @@ impl PPU for NesPPU {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartridge::{test::ines_image, Rom};
+    use crate::mapper;
+
+    // Eight sprites on scanline 20 fill the secondary OAM.
+    fn ppu_with_full_line() -> NesPPU {
+        let mut ppu = NesPPU::new(mapper::new(Rom::new(&ines_image(0, 1, 1)).unwrap()));
+        ppu.scanline = 20;
+        for sprite in 0..8 {
+            ppu.oam_data[sprite * 4] = 20;
+        }
+        ppu
+    }
+
+    fn sprite_overflow(ppu: &NesPPU) -> bool {
+        ppu.status.contains(StatusRegister::SPRITE_OVERFLOW)
+    }
+
+    #[test]
+    fn test_ninth_sprite_sets_overflow() {
+        let mut ppu = ppu_with_full_line();
+        ppu.oam_data[8 * 4] = 20;
+        ppu.sprite_evaluation();
+        assert!(sprite_overflow(&ppu));
+    }
+
+    #[test]
+    fn test_overflow_checks_bytes_diagonally() {
+        // the tile number of sprite 9 is read as its Y
+        let mut ppu = ppu_with_full_line();
+        ppu.oam_data[9 * 4 + 1] = 20;
+        ppu.sprite_evaluation();
+        assert!(sprite_overflow(&ppu));
+
+        // while the real Y of sprite 9 is skipped
+        let mut ppu = ppu_with_full_line();
+        ppu.oam_data[9 * 4] = 20;
+        ppu.sprite_evaluation();
+        assert!(!sprite_overflow(&ppu));
+        assert_eq!(ppu.secondary_oam_data.iter().filter(|sprite| sprite.is_some()).count(), 8);
+    }
+    #[test]
+    fn test_save_state_roundtrip() {
+        let mut ppu = ppu_with_full_line();
+        // tile $40 has pattern data, so the shifters and sprite patterns are filled in
+        ppu.vram[..0x3c0].fill(0x40);
+        ppu.oam_data[1] = 0x40;
+        ppu.mask = MaskRegister::SHOW_BACKGROUND | MaskRegister::SHOW_SPRITES;
+        for _ in 0..341 + 100 {
+            ppu.tick();
+        }
+        assert_ne!(ppu.sprite_patterns[0], (0, 0));
+
+        let mut w = StateWriter::new();
+        ppu.save_state(&mut w);
+        let saved = w.into_bytes();
+
+        let mut loaded = NesPPU::new(ppu.mapper.clone());
+        loaded.load_state(&mut StateReader::new(&saved)).unwrap();
+        let mut w = StateWriter::new();
+        loaded.save_state(&mut w);
+        assert_eq!(w.into_bytes(), saved);
+    }
+}
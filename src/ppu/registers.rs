@@ -1,11 +1,9 @@
-mod addr;
 mod control;
 mod mask;
 mod status;
-mod scroll;
+mod vram_addr;
 
-pub use addr::AddrRegister;
 pub use control::ControlRegister;
 pub use mask::MaskRegister;
 pub use status::StatusRegister;
-pub use scroll::ScrollRegister;
\ No newline at end of file
+pub use vram_addr::VramAddrRegister;
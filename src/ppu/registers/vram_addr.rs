@@ -0,0 +1,229 @@
+use crate::savestate::{Snapshot, StateReader, StateWriter};
+
+// PPUSCROLL and PPUADDR both write to the same internal registers:
+//
+// v: the current VRAM address, used by $2007 and by rendering
+// t: the temporary address, copied into v at the start of each frame and line
+// x: fine X scroll, 3 bits
+// w: the write toggle shared by $2005 and $2006
+//
+// The addresses use this layout:
+//
+//  yyy NN YYYYY XXXXX
+//  ||| || ||||| +++++-- coarse X scroll
+//  ||| || +++++-------- coarse Y scroll
+//  ||| ++-------------- nametable select
+//  +++----------------- fine Y scroll
+const COARSE_X: u16 = 0x001f;
+const COARSE_Y: u16 = 0x03e0;
+const NAMETABLE: u16 = 0x0c00;
+const FINE_Y: u16 = 0x7000;
+const HORIZONTAL_BITS: u16 = 0x041f;
+const VERTICAL_BITS: u16 = 0x7be0;
+
+pub struct VramAddrRegister {
+    pub v: u16,
+    pub t: u16,
+    pub fine_x: u8,
+    pub w: bool,
+}
+
+impl VramAddrRegister {
+    pub fn new() -> Self {
+        VramAddrRegister {
+            v: 0,
+            t: 0,
+            fine_x: 0,
+            w: false,
+        }
+    }
+
+    // $2000 write
+    pub fn write_ctrl(&mut self, data: u8) {
+        self.t = (self.t & !NAMETABLE) | ((data as u16 & 0b11) << 10);
+    }
+
+    // $2005 write, X scroll first then Y scroll
+    pub fn write_scroll(&mut self, data: u8) {
+        if !self.w {
+            self.t = (self.t & !COARSE_X) | (data as u16 >> 3);
+            self.fine_x = data & 0b111;
+        } else {
+            self.t = (self.t & !(FINE_Y | COARSE_Y)) | ((data as u16 & 0b111) << 12) | ((data as u16 >> 3) << 5);
+        }
+        self.w = !self.w;
+    }
+
+    // $2006 write, high byte first. v only changes on the second write.
+    pub fn write_addr(&mut self, data: u8) {
+        if !self.w {
+            // bit 14 of t is cleared as well
+            self.t = (self.t & 0x00ff) | ((data as u16 & 0x3f) << 8);
+        } else {
+            self.t = (self.t & 0xff00) | data as u16;
+            self.v = self.t;
+        }
+        self.w = !self.w;
+    }
+
+    pub fn reset_latch(&mut self) {
+        self.w = false;
+    }
+
+    pub fn reset(&mut self) {
+        self.t = 0;
+        self.fine_x = 0;
+        self.w = false;
+    }
+
+    // Address $2007 accesses, only 14 of the 15 bits reach the PPU bus.
+    pub fn get(&self) -> u16 {
+        self.v & 0x3fff
+    }
+
+    pub fn increment(&mut self, inc: u8) {
+        self.v = self.v.wrapping_add(inc as u16) & 0x7fff;
+    }
+
+    pub fn fine_y(&self) -> u16 {
+        (self.v & FINE_Y) >> 12
+    }
+
+    // Moves v to the next tile, into the next nametable at the right edge.
+    pub fn increment_x(&mut self) {
+        if self.v & COARSE_X == 31 {
+            self.v &= !COARSE_X;
+            self.v ^= 0x0400;
+        } else {
+            self.v += 1;
+        }
+    }
+
+    pub fn increment_y(&mut self) {
+        if self.v & FINE_Y != FINE_Y {
+            self.v += 0x1000;
+            return;
+        }
+        self.v &= !FINE_Y;
+        let mut coarse_y = (self.v & COARSE_Y) >> 5;
+        if coarse_y == 29 {
+            coarse_y = 0;
+            self.v ^= 0x0800;
+        } else if coarse_y == 31 {
+            // rows 30 and 31 hold the attributes, scrolling into them wraps without switching nametables
+            coarse_y = 0;
+        } else {
+            coarse_y += 1;
+        }
+        self.v = (self.v & !COARSE_Y) | (coarse_y << 5);
+    }
+
+    pub fn copy_horizontal(&mut self) {
+        self.v = (self.v & !HORIZONTAL_BITS) | (self.t & HORIZONTAL_BITS);
+    }
+
+    pub fn copy_vertical(&mut self) {
+        self.v = (self.v & !VERTICAL_BITS) | (self.t & VERTICAL_BITS);
+    }
+}
+
+impl Snapshot for VramAddrRegister {
+    fn save_state(&self, w: &mut StateWriter) {
+        w.write_u16(self.v);
+        w.write_u16(self.t);
+        w.write_u8(self.fine_x);
+        w.write_bool(self.w);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), String> {
+        self.v = r.read_u16()?;
+        self.t = r.read_u16()?;
+        self.fine_x = r.read_u8()?;
+        self.w = r.read_bool()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scroll_and_addr_writes() {
+        let mut reg = VramAddrRegister::new();
+        reg.write_ctrl(0);
+        reg.write_scroll(0x7d);
+        assert_eq!(reg.t, 0x000f);
+        assert_eq!(reg.fine_x, 0b101);
+        reg.write_scroll(0x5e);
+        assert_eq!(reg.t, 0x616f);
+        assert!(!reg.w);
+
+        reg.write_addr(0x3d);
+        assert_eq!(reg.t, 0x3d6f);
+        assert_eq!(reg.v, 0);
+        reg.write_addr(0xf0);
+        assert_eq!(reg.t, 0x3df0);
+        assert_eq!(reg.v, 0x3df0);
+        assert!(!reg.w);
+    }
+
+    #[test]
+    fn test_scroll_and_addr_share_toggle() {
+        let mut reg = VramAddrRegister::new();
+        reg.write_scroll(0x08);
+        // counts as the second $2006 write, so v is loaded straight away
+        reg.write_addr(0x12);
+        assert_eq!(reg.v, 0x0012);
+        assert!(!reg.w);
+    }
+
+    #[test]
+    fn test_addr_write_clears_bit_14() {
+        let mut reg = VramAddrRegister::new();
+        reg.t = 0x4000;
+        reg.write_addr(0xff);
+        assert_eq!(reg.t, 0x3f00);
+    }
+
+    #[test]
+    fn test_increment_x_wraps_into_next_nametable() {
+        let mut reg = VramAddrRegister::new();
+        reg.v = 0x001e;
+        reg.increment_x();
+        assert_eq!(reg.v, 0x001f);
+        reg.increment_x();
+        assert_eq!(reg.v, 0x0400);
+
+        reg.v = 0x041f;
+        reg.increment_x();
+        assert_eq!(reg.v, 0x0000);
+    }
+
+    #[test]
+    fn test_increment_y() {
+        let mut reg = VramAddrRegister::new();
+        reg.increment_y();
+        assert_eq!(reg.v, 0x1000);
+
+        // the last tile row moves down to the nametable below
+        reg.v = FINE_Y | (29 << 5);
+        reg.increment_y();
+        assert_eq!(reg.v, 0x0800);
+
+        // out of the attribute rows it wraps within the same nametable
+        reg.v = FINE_Y | 0x0800 | (31 << 5);
+        reg.increment_y();
+        assert_eq!(reg.v, 0x0800);
+    }
+
+    #[test]
+    fn test_copy_horizontal_and_vertical() {
+        let mut reg = VramAddrRegister::new();
+        reg.t = 0x7fff;
+        reg.copy_horizontal();
+        assert_eq!(reg.v, HORIZONTAL_BITS);
+        reg.copy_vertical();
+        assert_eq!(reg.v, 0x7fff);
+    }
+}
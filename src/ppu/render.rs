@@ -0,0 +1,288 @@
+use super::{LineStatus, NesPPU, TileId};
+use crate::renderer::palette::SYSTEM_PALLETE;
+
+impl NesPPU {
+    // Runs the fetches and draws the pixel of the current dot.
+    pub(super) fn render_dot(&mut self) {
+        let is_visible = match LineStatus::from(self.scanline) {
+            LineStatus::Visible => true,
+            LineStatus::PreRender => false,
+            _ => return,
+        };
+        let dot = self.cycles;
+
+        if self.is_rendering_enabled() {
+            if (2..=257).contains(&dot) || (322..=337).contains(&dot) {
+                self.shift_background();
+            }
+            if (1..=257).contains(&dot) || (321..=337).contains(&dot) {
+                self.fetch_background(dot);
+            }
+            match dot {
+                256 => self.vram_addr.increment_y(),
+                257 => {
+                    self.load_background();
+                    self.vram_addr.copy_horizontal();
+                    if is_visible {
+                        self.sprite_evaluation();
+                    } else {
+                        // nothing is drawn on the pre-render line, so no sprites are found for line 0
+                        self.secondary_oam_data = [None; 8];
+                    }
+                }
+                280..=304 if !is_visible => self.vram_addr.copy_vertical(),
+                _ => {}
+            }
+            // each sprite slot has its pattern fetched over 8 dots from 257 to 320
+            if (257..=320).contains(&dot) && (dot - 257) % 8 == 7 {
+                self.fetch_sprite((dot - 257) / 8);
+            }
+        }
+
+        if is_visible && (1..=256).contains(&dot) {
+            self.draw_pixel(dot - 1);
+        }
+    }
+
+    fn read_nametable(&self, addr: u16) -> u8 {
+        self.vram[self.mirror_vram_addr(addr) as usize]
+    }
+
+    // Tiles take 8 dots to fetch: nametable byte, attribute byte, then the two pattern planes.
+    fn fetch_background(&mut self, dot: usize) {
+        let v = self.vram_addr.v;
+        match (dot - 1) % 8 {
+            0 => {
+                self.load_background();
+                self.bg_next_tile = self.read_nametable(0x2000 | (v & 0x0fff));
+            }
+            2 => {
+                let attr = self.read_nametable(0x23c0 | (v & 0x0c00) | ((v >> 4) & 0x38) | ((v >> 2) & 0x07));
+                // each attribute byte covers 4x4 tiles, 2 bits per 2x2 quadrant
+                let shift = ((v >> 4) & 0x04) | (v & 0x02);
+                self.bg_next_attr = (attr >> shift) & 0b11;
+            }
+            4 => self.bg_next_lo = self.read_chr(self.bg_pattern_addr()),
+            6 => self.bg_next_hi = self.read_chr(self.bg_pattern_addr() + 8),
+            7 => self.vram_addr.increment_x(),
+            _ => {}
+        }
+    }
+
+    fn bg_pattern_addr(&self) -> u16 {
+        self.ctrl.bknd_pattern_addr() + self.bg_next_tile as u16 * 16 + self.vram_addr.fine_y()
+    }
+
+    // The fetched tile goes into the low byte of the shifters, behind the one being drawn.
+    fn load_background(&mut self) {
+        self.bg_pattern_lo = (self.bg_pattern_lo & 0xff00) | self.bg_next_lo as u16;
+        self.bg_pattern_hi = (self.bg_pattern_hi & 0xff00) | self.bg_next_hi as u16;
+        let attr_lo = if self.bg_next_attr & 0b01 != 0 { 0xff } else { 0x00 };
+        let attr_hi = if self.bg_next_attr & 0b10 != 0 { 0xff } else { 0x00 };
+        self.bg_attr_lo = (self.bg_attr_lo & 0xff00) | attr_lo;
+        self.bg_attr_hi = (self.bg_attr_hi & 0xff00) | attr_hi;
+    }
+
+    fn shift_background(&mut self) {
+        self.bg_pattern_lo <<= 1;
+        self.bg_pattern_hi <<= 1;
+        self.bg_attr_lo <<= 1;
+        self.bg_attr_hi <<= 1;
+    }
+
+    fn fetch_sprite(&mut self, slot: usize) {
+        let sprite = match self.secondary_oam_data[slot] {
+            Some(sprite) => sprite,
+            None => {
+                self.sprite_patterns[slot] = (0, 0);
+                return;
+            }
+        };
+        let height = match sprite.tile_id {
+            TileId::Normal { .. } => 8,
+            TileId::Large { .. } => 16,
+        };
+        let mut row = self.scanline - sprite.y as u16;
+        if sprite.attr.is_flip_vertical {
+            row = height - 1 - row;
+        }
+        // 8x16 sprites are two tiles on top of each other, the bank comes from the tile number
+        let tile_addr = match sprite.tile_id {
+            TileId::Normal { id } => self.ctrl.sprt_pattern_addr() + id as u16 * 16,
+            TileId::Large { bank, id } => bank + (id as u16 + row / 8) * 16,
+        };
+        let mut lo = self.read_chr(tile_addr + row % 8);
+        let mut hi = self.read_chr(tile_addr + row % 8 + 8);
+        // store the planes left to right so drawing doesn't have to care about flipping
+        if sprite.attr.is_flip_horizonal {
+            lo = lo.reverse_bits();
+            hi = hi.reverse_bits();
+        }
+        self.sprite_patterns[slot] = (lo, hi);
+    }
+
+    fn draw_pixel(&mut self, x: usize) {
+        let mut bg_pixel = 0;
+        let mut bg_palette = 0;
+        if self.mask.show_background() && (x >= 8 || self.mask.leftmost_8pixel_background()) {
+            let bit = 0x8000 >> self.vram_addr.fine_x;
+            bg_pixel = ((self.bg_pattern_hi & bit != 0) as u8) << 1 | (self.bg_pattern_lo & bit != 0) as u8;
+            bg_palette = ((self.bg_attr_hi & bit != 0) as u8) << 1 | (self.bg_attr_lo & bit != 0) as u8;
+        }
+
+        // The first opaque sprite in OAM order wins, even when it's behind the
+        // background and a later sprite in front would have shown.
+        let mut sprite_pixel = None;
+        if self.mask.show_sprites() && (x >= 8 || self.mask.leftmost_8pixel_sprites()) {
+            for (slot, sprite) in self.secondary_oam_data.iter().enumerate() {
+                let sprite = match sprite {
+                    Some(sprite) => sprite,
+                    None => break,
+                };
+                let offset = x.wrapping_sub(sprite.x as usize);
+                if offset >= 8 {
+                    continue;
+                }
+                let (lo, hi) = self.sprite_patterns[slot];
+                let shift = 7 - offset;
+                let pixel = ((hi >> shift) & 1) << 1 | ((lo >> shift) & 1);
+                if pixel != 0 {
+                    sprite_pixel = Some((pixel, sprite.attr.palette, sprite.attr.priority == 1));
+                    // sprite 0 can only ever be in the first slot, so it always wins when opaque
+                    if self.sprite_zero_flags[slot] && bg_pixel != 0 && x != 255 {
+                        self.status.set_sprite_zero_hit(true);
+                    }
+                    break;
+                }
+            }
+        }
+
+        let palette_idx = match sprite_pixel {
+            Some((_, _, true)) if bg_pixel != 0 => bg_palette << 2 | bg_pixel,
+            Some((pixel, palette, _)) => 0x10 | palette << 2 | pixel,
+            None if bg_pixel != 0 => bg_palette << 2 | bg_pixel,
+            None => 0,
+        };
+        let mut color = self.palette_table[palette_idx as usize];
+        if self.mask.is_grayscale() {
+            color &= 0x30;
+        }
+        self.frame.set_pixel(x, self.scanline as usize, SYSTEM_PALLETE[(color & 0x3f) as usize]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartridge::{test::ines_image, Rom};
+    use crate::mapper;
+    use crate::ppu::registers::{MaskRegister, StatusRegister};
+    use crate::ppu::Sprite;
+
+    fn ppu() -> NesPPU {
+        NesPPU::new(mapper::new(Rom::new(&ines_image(0, 1, 1)).unwrap()))
+    }
+
+    fn pixel(ppu: &NesPPU, x: usize, y: usize) -> (u8, u8, u8) {
+        let base = (y * 256 + x) * 3;
+        let data = &ppu.frame.data;
+        (data[base], data[base + 1], data[base + 2])
+    }
+
+    // Runs the pre-render line and the first visible line.
+    fn render_first_line(ppu: &mut NesPPU) {
+        ppu.scanline = 261;
+        ppu.cycles = 0;
+        while ppu.scanline != 1 {
+            ppu.tick();
+        }
+    }
+
+    #[test]
+    fn test_background_fine_x_scroll() {
+        let mut ppu = ppu();
+        // CHR from $0400 holds 1 in both planes, so tile $40 only has its rightmost pixel set
+        ppu.vram[0] = 0x40;
+        ppu.palette_table[0] = 0x0f;
+        ppu.palette_table[3] = 0x30;
+        ppu.mask = MaskRegister::SHOW_BACKGROUND | MaskRegister::LEFTMOST_8PIXEL_BACKGROUND;
+
+        render_first_line(&mut ppu);
+        assert_eq!(pixel(&ppu, 6, 0), SYSTEM_PALLETE[0x0f]);
+        assert_eq!(pixel(&ppu, 7, 0), SYSTEM_PALLETE[0x30]);
+
+        ppu.vram_addr.write_scroll(1);
+        ppu.vram_addr.write_scroll(0);
+        render_first_line(&mut ppu);
+        assert_eq!(pixel(&ppu, 6, 0), SYSTEM_PALLETE[0x30]);
+        assert_eq!(pixel(&ppu, 7, 0), SYSTEM_PALLETE[0x0f]);
+    }
+    // Puts sprite 0 on the current line at `x` with every pixel opaque, over a
+    // background that is opaque everywhere when `opaque_background` is set.
+    fn sprite_zero_over_background(x: u8, opaque_background: bool) -> NesPPU {
+        let mut ppu = ppu();
+        ppu.scanline = 10;
+        ppu.secondary_oam_data[0] = Some(Sprite::new(false, &[9, 0, 0, x]));
+        ppu.sprite_zero_flags[0] = true;
+        ppu.sprite_patterns[0] = (0xff, 0x00);
+        if opaque_background {
+            ppu.bg_pattern_lo = 0xffff;
+        }
+        ppu.mask = MaskRegister::SHOW_BACKGROUND
+            | MaskRegister::SHOW_SPRITES
+            | MaskRegister::LEFTMOST_8PIXEL_BACKGROUND
+            | MaskRegister::LEFTMOST_8PIXEL_SPRITES;
+        ppu
+    }
+
+    fn sprite_zero_hit(ppu: &NesPPU) -> bool {
+        ppu.status.contains(StatusRegister::SPRITE_ZERO_HIT)
+    }
+
+    #[test]
+    fn test_sprite_zero_hit_needs_opaque_overlap() {
+        let mut ppu = sprite_zero_over_background(20, false);
+        ppu.draw_pixel(20);
+        assert!(!sprite_zero_hit(&ppu));
+
+        let mut ppu = sprite_zero_over_background(20, true);
+        ppu.sprite_patterns[0] = (0x00, 0x00);
+        ppu.draw_pixel(20);
+        assert!(!sprite_zero_hit(&ppu));
+
+        let mut ppu = sprite_zero_over_background(20, true);
+        ppu.draw_pixel(19);
+        assert!(!sprite_zero_hit(&ppu));
+        ppu.draw_pixel(20);
+        assert!(sprite_zero_hit(&ppu));
+    }
+
+    #[test]
+    fn test_no_sprite_zero_hit_at_x_255() {
+        let mut ppu = sprite_zero_over_background(250, true);
+        ppu.draw_pixel(255);
+        assert!(!sprite_zero_hit(&ppu));
+        ppu.draw_pixel(254);
+        assert!(sprite_zero_hit(&ppu));
+    }
+
+    #[test]
+    fn test_no_sprite_zero_hit_in_clipped_left_column() {
+        let mut ppu = sprite_zero_over_background(0, true);
+        ppu.mask.remove(MaskRegister::LEFTMOST_8PIXEL_SPRITES);
+        ppu.draw_pixel(5);
+        assert!(!sprite_zero_hit(&ppu));
+
+        let mut ppu = sprite_zero_over_background(0, true);
+        ppu.mask.remove(MaskRegister::LEFTMOST_8PIXEL_BACKGROUND);
+        ppu.draw_pixel(5);
+        assert!(!sprite_zero_hit(&ppu));
+
+        let mut ppu = sprite_zero_over_background(4, true);
+        ppu.mask.remove(MaskRegister::LEFTMOST_8PIXEL_BACKGROUND);
+        ppu.draw_pixel(7);
+        assert!(!sprite_zero_hit(&ppu));
+        ppu.draw_pixel(8);
+        assert!(sprite_zero_hit(&ppu));
+    }
+}
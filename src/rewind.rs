@@ -0,0 +1,169 @@
+use std::collections::VecDeque;
+
+use crate::bus::Bus;
+use crate::cpu::CPU;
+use crate::savestate;
+
+// Rewind history is only ever restored into the running game, so the ROM hash is not needed.
+const REWIND_HASH: u64 = 0;
+
+// Ring buffer of save states taken every few frames.
+// Only the newest snapshot is kept whole. Older ones are stored as the XOR against
+// their successor, run-length encoded, which is mostly zeros from frame to frame.
+pub struct Rewind {
+    latest: Option<Vec<u8>>,
+    deltas: VecDeque<Vec<u8>>,
+    used_bytes: usize,
+    budget_bytes: usize,
+    interval: u32,
+    frames: u32,
+}
+
+impl Rewind {
+    pub fn new(budget_bytes: usize, interval: u32) -> Self {
+        Rewind {
+            latest: None,
+            deltas: VecDeque::new(),
+            used_bytes: 0,
+            budget_bytes,
+            interval: interval.max(1),
+            frames: 0,
+        }
+    }
+
+    pub fn on_frame(&mut self, cpu: &CPU<Bus>) {
+        self.frames += 1;
+        if self.frames >= self.interval {
+            self.frames = 0;
+            self.push(savestate::save(cpu, REWIND_HASH));
+        }
+    }
+
+    // Restores the newest snapshot and drops it from the history.
+    // The oldest snapshot stays put, so holding the key parks the game there.
+    pub fn step_back(&mut self, cpu: &mut CPU<Bus>) {
+        let latest = match self.latest.as_mut() {
+            Some(latest) => latest,
+            None => return,
+        };
+        if let Err(err) = savestate::load(cpu, latest, REWIND_HASH) {
+            eprintln!("Failed to rewind: {}", err);
+            self.clear();
+            return;
+        }
+        if let Some(delta) = self.deltas.pop_back() {
+            self.used_bytes -= delta.len();
+            apply_delta(latest, &delta);
+        }
+        self.frames = 0;
+    }
+
+    pub fn clear(&mut self) {
+        self.latest = None;
+        self.deltas.clear();
+        self.used_bytes = 0;
+    }
+
+    fn push(&mut self, state: Vec<u8>) {
+        if let Some(latest) = self.latest.as_ref() {
+            if latest.len() == state.len() {
+                let delta = encode_delta(latest, &state);
+                self.used_bytes += delta.len();
+                self.deltas.push_back(delta);
+            } else {
+                self.clear();
+            }
+        }
+        self.used_bytes += state.len();
+        if let Some(latest) = self.latest.replace(state) {
+            self.used_bytes -= latest.len();
+        }
+
+        while self.used_bytes > self.budget_bytes {
+            match self.deltas.pop_front() {
+                Some(delta) => self.used_bytes -= delta.len(),
+                None => break,
+            }
+        }
+    }
+}
+
+// XOR of two equally sized snapshots as alternating runs:
+// u16 count of zero bytes, u16 count of literal bytes, then the literals.
+fn encode_delta(older: &[u8], newer: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut pos = 0;
+    while pos < older.len() {
+        let start = pos;
+        while pos < older.len() && pos - start < u16::MAX as usize && older[pos] == newer[pos] {
+            pos += 1;
+        }
+        out.extend_from_slice(&((pos - start) as u16).to_le_bytes());
+
+        let start = pos;
+        while pos < older.len() && pos - start < u16::MAX as usize && older[pos] != newer[pos] {
+            pos += 1;
+        }
+        out.extend_from_slice(&((pos - start) as u16).to_le_bytes());
+        out.extend(older[start..pos].iter().zip(&newer[start..pos]).map(|(a, b)| a ^ b));
+    }
+    out
+}
+
+// Turns `state` back into the snapshot the delta was taken against.
+fn apply_delta(state: &mut [u8], delta: &[u8]) {
+    let mut pos = 0;
+    let mut i = 0;
+    while i < delta.len() {
+        pos += u16::from_le_bytes([delta[i], delta[i + 1]]) as usize;
+        let literals = u16::from_le_bytes([delta[i + 2], delta[i + 3]]) as usize;
+        i += 4;
+        for byte in &delta[i..i + literals] {
+            state[pos] ^= byte;
+            pos += 1;
+        }
+        i += literals;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // applying the delta to the newer snapshot has to give back the older one
+    fn roundtrip(older: &[u8], newer: &[u8]) -> Vec<u8> {
+        let delta = encode_delta(older, newer);
+        let mut state = newer.to_vec();
+        apply_delta(&mut state, &delta);
+        assert_eq!(state, older);
+        delta
+    }
+
+    #[test]
+    fn test_delta_identical() {
+        let state: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+        let delta = roundtrip(&state, &state);
+        assert_eq!(delta, vec![0xE8, 0x03, 0, 0]);
+    }
+
+    #[test]
+    fn test_delta_all_different() {
+        let older = vec![0x55; 1000];
+        let newer = vec![0xAA; 1000];
+        let delta = roundtrip(&older, &newer);
+        assert_eq!(delta.len(), 4 + 1000);
+    }
+
+    #[test]
+    fn test_delta_runs_longer_than_count() {
+        let len = u16::MAX as usize * 2 + 100;
+        let older = vec![0; len];
+        roundtrip(&older, &older);
+        roundtrip(&older, &vec![1; len]);
+
+        let mut newer = older.clone();
+        newer[u16::MAX as usize + 10] = 7;
+        newer[len - 1] = 7;
+        roundtrip(&older, &newer);
+    }
+}
@@ -0,0 +1,168 @@
+use crate::bus::Bus;
+use crate::cpu::CPU;
+
+const MAGIC: [u8; 4] = *b"NESS";
+const VERSION: u16 = 6;
+const HEADER_LEN: usize = MAGIC.len() + 2 + 8;
+
+pub trait Snapshot {
+    fn save_state(&self, w: &mut StateWriter);
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), String>;
+}
+
+pub struct StateWriter {
+    buf: Vec<u8>,
+}
+
+impl StateWriter {
+    pub fn new() -> Self {
+        StateWriter { buf: Vec::new() }
+    }
+
+    pub fn write_u8(&mut self, value: u8) {
+        self.buf.push(value);
+    }
+
+    pub fn write_bool(&mut self, value: bool) {
+        self.buf.push(value as u8);
+    }
+
+    pub fn write_u16(&mut self, value: u16) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn write_u32(&mut self, value: u32) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn write_u64(&mut self, value: u64) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn write_usize(&mut self, value: usize) {
+        self.write_u64(value as u64);
+    }
+
+    // Length-prefixed so that a size mismatch is caught on load.
+    pub fn write_bytes(&mut self, data: &[u8]) {
+        self.write_u32(data.len() as u32);
+        self.buf.extend_from_slice(data);
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+impl Default for StateWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct StateReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> StateReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        StateReader { data, pos: 0 }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        if self.pos + len > self.data.len() {
+            return Err("Save state is truncated".to_string());
+        }
+        let slice = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(slice)
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn read_bool(&mut self) -> Result<bool, String> {
+        Ok(self.read_u8()? != 0)
+    }
+
+    pub fn read_u16(&mut self) -> Result<u16, String> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    pub fn read_u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    pub fn read_usize(&mut self) -> Result<usize, String> {
+        Ok(self.read_u64()? as usize)
+    }
+
+    pub fn read_bytes(&mut self) -> Result<&'a [u8], String> {
+        let len = self.read_u32()? as usize;
+        self.take(len)
+    }
+
+    // Reads a length-prefixed block into a buffer of exactly the same size.
+    pub fn read_into(&mut self, buf: &mut [u8]) -> Result<(), String> {
+        let data = self.read_bytes()?;
+        if data.len() != buf.len() {
+            return Err(format!(
+                "Save state block is {} bytes, expected {}",
+                data.len(),
+                buf.len()
+            ));
+        }
+        buf.copy_from_slice(data);
+        Ok(())
+    }
+}
+
+// FNV-1a, used to tie a save state to the ROM it was taken from.
+pub fn rom_hash(data: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in data {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+pub fn save(cpu: &CPU<Bus>, rom_hash: u64) -> Vec<u8> {
+    let mut w = StateWriter::new();
+    w.buf.extend_from_slice(&MAGIC);
+    w.write_u16(VERSION);
+    w.write_u64(rom_hash);
+    cpu.save_state(&mut w);
+    w.into_bytes()
+}
+
+pub fn load(cpu: &mut CPU<Bus>, data: &[u8], rom_hash: u64) -> Result<(), String> {
+    let mut r = StateReader::new(data);
+    if r.take(4)? != MAGIC {
+        return Err("Not a save state file".to_string());
+    }
+    let version = r.read_u16()?;
+    if version != VERSION {
+        return Err(format!("Unsupported save state version {}", version));
+    }
+    if r.read_u64()? != rom_hash {
+        return Err("Save state was made with a different ROM".to_string());
+    }
+
+    // keep the current machine intact if the body turns out to be damaged
+    let backup = save(cpu, rom_hash);
+    if let Err(err) = cpu.load_state(&mut r) {
+        let mut r = StateReader::new(&backup[HEADER_LEN..]);
+        return match cpu.load_state(&mut r) {
+            Ok(()) => Err(err),
+            Err(restore_err) => Err(format!("{}, and the previous state could not be restored: {}", err, restore_err)),
+        };
+    }
+    Ok(())
+}
@@ -1,34 +1,44 @@
 use once_cell::sync::Lazy;
 
+use crate::bus::Bus;
 use crate::cpu::AddressingMode;
 use crate::cpu::Mem;
 use crate::cpu::CPU;
 use crate::opcodes;
-use std::collections::HashMap;
 
-pub static NON_READABLE_ADDR: Lazy<Vec<u16>> = Lazy::new(|| vec!(0x2000, 0x2001, 0x2003, 0x2005, 0x2006, 0x4014));
-
-pub fn trace(cpu: &mut CPU) -> String {
-    let ref opscodes: HashMap<u8, &'static opcodes::OpCode> = *opcodes::OPCODE_MAP;
+// PPU, APU and controller registers. They are either write-only or change
+// state when read ($2002 clears vblank, $2007 moves v, $4015 acknowledges the
+// frame IRQ, $4016 shifts the controller), so the trace never reads them.
+pub static NON_READABLE_ADDR: Lazy<Vec<u16>> = Lazy::new(|| {
+    let mut addrs: Vec<u16> = (0x2000..=0x2007).collect();
+    addrs.extend(0x4000..=0x4017);
+    addrs
+});
 
+pub fn trace(cpu: &mut CPU<Bus>) -> String {
     let ref non_readable_addr = *NON_READABLE_ADDR;
+    // the reads below are only for display, put back the data bus latch they disturb
+    let open_bus = cpu.bus.open_bus();
 
-    let code = cpu.mem_read(cpu.program_counter);
-    let ops = opscodes.get(&code).unwrap();
+    let code = cpu.bus.mem_read(cpu.program_counter);
+    let ops = opcodes::OPCODE_TABLE[code as usize];
 
     let begin = cpu.program_counter;
     let mut hex_dump = vec![];
     hex_dump.push(code);
 
     let (mem_addr, stored_value) = match ops.mode {
-        AddressingMode::Immediate | AddressingMode::NoneAddressing => (0, 0),
+        AddressingMode::Immediate | AddressingMode::NoneAddressing | AddressingMode::Indirect => (0, 0),
         _ => {
-            let (addr, _) = cpu.get_absolute_address(&ops.mode, begin + 1);
+            let addr = cpu.get_absolute_address(&ops.mode, begin + 1);
+            // registers are mirrored every 8 bytes up to $3FFF
+            let register = if (0x2000..0x4000).contains(&addr) { addr & 0x2007 } else { addr };
 
-            if !non_readable_addr.contains(&addr) {
-                (addr, cpu.mem_read(addr))
+            if !non_readable_addr.contains(&register) {
+                (addr, cpu.bus.mem_read(addr))
             } else {
-                (addr, 0)
+                // shown as FF, the same as in the reference nestest.log
+                (addr, 0xff)
             }
         }
     };
@@ -39,8 +49,8 @@ pub fn trace(cpu: &mut CPU) -> String {
             _ => String::from(""),
         },
         2 => {
-            let address: u8 = cpu.mem_read(begin + 1);
-            // let value = cpu.mem_read(address));
+            let address: u8 = cpu.bus.mem_read(begin + 1);
+            // let value = cpu.bus.mem_read(address));
             hex_dump.push(address);
 
             match ops.mode {
@@ -82,26 +92,26 @@ pub fn trace(cpu: &mut CPU) -> String {
             }
         }
         3 => {
-            let address_lo = cpu.mem_read(begin + 1);
-            let address_hi = cpu.mem_read(begin + 2);
+            let address_lo = cpu.bus.mem_read(begin + 1);
+            let address_hi = cpu.bus.mem_read(begin + 2);
             hex_dump.push(address_lo);
             hex_dump.push(address_hi);
 
-            let address = cpu.mem_read_u16(begin + 1);
+            let address = cpu.bus.mem_read_u16(begin + 1);
 
             match ops.mode {
-                AddressingMode::NoneAddressing => {
+                AddressingMode::NoneAddressing | AddressingMode::Indirect => {
                     if ops.code == 0x6c {
                         //jmp indirect
                         let jmp_addr = if address & 0x00FF == 0x00FF {
-                            let lo = cpu.mem_read(address);
-                            let hi = cpu.mem_read(address & 0xFF00);
+                            let lo = cpu.bus.mem_read(address);
+                            let hi = cpu.bus.mem_read(address & 0xFF00);
                             (hi as u16) << 8 | (lo as u16)
                         } else {
-                            cpu.mem_read_u16(address)
+                            cpu.bus.mem_read_u16(address)
                         };
 
-                        // let jmp_addr = cpu.mem_read_u16(address);
+                        // let jmp_addr = cpu.bus.mem_read_u16(address);
                         format!("(${:04x}) = {:04x}", address, jmp_addr)
                     } else {
                         format!("${:04x}", address)
@@ -134,9 +144,41 @@ pub fn trace(cpu: &mut CPU) -> String {
         .trim()
         .to_string();
 
+    cpu.bus.set_open_bus(open_bus);
+    let (scanline, dot) = cpu.bus.ppu().position();
     format!(
-        "{:47} A:{:02x} X:{:02x} Y:{:02x} P:{:02x} SP:{:02x}",
+        "{:47} A:{:02x} X:{:02x} Y:{:02x} P:{:02x} SP:{:02x} PPU:{:>3},{:>3} CYC:{}",
         asm_str, cpu.reg_a, cpu.reg_x, cpu.reg_y, cpu.status, cpu.stack_pointer,
+        scanline, dot, cpu.bus.cycles(),
     )
     .to_ascii_uppercase()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apu::{sounds::SoundManager, NesAPU};
+    use crate::cartridge::{test::ines_image, Rom};
+    use crate::joypad::{Joypad, JoypadButton};
+    use crate::ppu::NesPPU;
+
+    #[test]
+    fn test_trace_does_not_read_registers() {
+        let rom = Rom::new(&ines_image(0, 1, 1)).unwrap();
+        let (_sound, txs) = SoundManager::new(44100.0);
+        let bus = Bus::new(rom, |_: &NesPPU, _: &mut Joypad| {}, NesAPU::new(txs));
+        let mut cpu = CPU::new(bus);
+        cpu.bus.joypad1().set_buttons(JoypadButton::BUTTON_A);
+        // LDA $4016
+        for (addr, data) in [0xad, 0x16, 0x40].iter().enumerate() {
+            cpu.bus.mem_write(addr as u16, *data);
+        }
+        cpu.program_counter = 0;
+
+        assert!(trace(&mut cpu).contains("LDA $4016 = FF"));
+        trace(&mut cpu);
+        cpu.step();
+        // the controller still hands out A first
+        assert_eq!(cpu.reg_a & 1, 1);
+    }
+}
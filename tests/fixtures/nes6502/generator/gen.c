@@ -0,0 +1,129 @@
+// Generates SingleStepTests-format vectors for one opcode from perfect6502,
+// the transistor-level simulation of the NMOS 6502 (https://github.com/mist64/perfect6502).
+//
+//   cc -O2 -I<perfect6502> gen.c <perfect6502>/perfect6502.c <perfect6502>/netlist_sim.c -o gen
+//   ./gen a9 300 > a9.json
+//
+// Every vector starts from random registers and 64K of random memory with the
+// opcode at a random PC. A preamble loads the registers, memory is swapped for
+// the test image on the fetch of the opcode, and every bus cycle is recorded
+// up to the next opcode fetch.
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include "types.h"
+#include "netlist_sim.h"
+#include "perfect6502.h"
+
+#define CLK0 1171
+#define SYNC 539
+
+static unsigned long long rng;
+static unsigned rnd(void) {
+    rng ^= rng << 13; rng ^= rng >> 7; rng ^= rng << 17;
+    return (unsigned)(rng >> 32);
+}
+
+static void *st;
+
+// phi1 then phi2 up to the point where address and R/W are valid
+static void clock_to_phi2(void) {
+    setNode(st, CLK0, 0);
+    recalcNodeList(st);
+    setNode(st, CLK0, 1);
+    recalcNodeList(st);
+}
+
+static void transfer(void) {
+    uint16_t ab = readAddressBus(st);
+    if (readRW(st)) writeDataBus(st, memory[ab]);
+    else memory[ab] = readDataBus(st);
+}
+
+static int decimal_op(int op) {
+    static const int ops[] = {0x61,0x65,0x69,0x6D,0x71,0x75,0x79,0x7D,0xE1,0xE5,0xE9,0xEB,0xED,0xF1,0xF5,0xF9,0xFD,
+        0x63,0x67,0x6F,0x73,0x77,0x7B,0x7F,0xE3,0xE7,0xEF,0xF3,0xF7,0xFB,0xFF,0x6B};
+    for (unsigned i = 0; i < sizeof ops / sizeof *ops; i++) if (ops[i] == op) return 1;
+    return 0;
+}
+
+static uint8_t image[65536], final_mem[65536];
+
+int main(int argc, char **argv) {
+    int op = strtol(argv[1], NULL, 16);
+    int count = atoi(argv[2]);
+    rng = 0x9E3779B97F4A7C15ULL ^ ((unsigned long long)(op + 1) * 0x100000001B3ULL);
+    for (int i = 0; i < 20; i++) rnd();
+
+    printf("[\n");
+    for (int t = 0; t < count; t++) {
+        uint16_t pc = rnd();
+        uint8_t s = rnd(), a = rnd(), x = rnd(), y = rnd();
+        uint8_t p = (rnd() & 0xCF) | 0x20;
+        if (decimal_op(op)) p &= ~0x08;
+        for (int i = 0; i < 65536; i++) image[i] = rnd();
+        image[pc] = op;
+
+        // preamble at $0200, memory is swapped before the fetch of the opcode under test:
+        // LDX #s; TXS; LDA #p; PHA; LDA #a; LDX #x; LDY #y; PLP; JMP pc
+        uint16_t q = 0x0200;
+        uint8_t code[] = {0xA2, s, 0x9A, 0xA9, p, 0x48, 0xA9, a, 0xA2, x, 0xA0, y, 0x28, 0x4C, pc & 0xFF, pc >> 8};
+        memset(memory, 0, sizeof memory);
+        memcpy(memory + q, code, sizeof code);
+        memory[0xFFFC] = q & 0xFF;
+        memory[0xFFFD] = q >> 8;
+
+        st = initAndResetChip();
+        int after_jmp = 0, guard = 0;
+        for (;;) {
+            clock_to_phi2();
+            if (isNodeHigh(st, SYNC)) {
+                // the fetch after the preamble's JMP is the opcode under test
+                if (after_jmp) break;
+                after_jmp = readAddressBus(st) == q + 13;
+            }
+            transfer();
+            if (++guard > 200) { fprintf(stderr, "%02x: preamble ran away\n", op); return 1; }
+        }
+        if (readAddressBus(st) != pc) { fprintf(stderr, "%02x: preamble landed at %04x, not %04x\n", op, readAddressBus(st), pc); return 1; }
+        memcpy(memory, image, sizeof memory);
+
+        uint16_t addr[64]; uint8_t data[64]; int wr[64]; int n = 0;
+        for (;;) {
+            transfer();
+            addr[n] = readAddressBus(st); data[n] = memory[addr[n]]; wr[n] = !readRW(st); n++;
+            if (n >= 60) { fprintf(stderr, "%02x: runaway\n", op); return 1; }
+            clock_to_phi2();
+            if (isNodeHigh(st, SYNC)) break;
+        }
+        // the next opcode fetch: the instruction is over
+        uint16_t next = readAddressBus(st);
+        memcpy(final_mem, memory, sizeof memory);
+        // feed it a NOP and give the unofficial opcodes, whose result lands a cycle later, time to settle
+        memory[next] = 0xEA;
+        transfer();
+        clock_to_phi2();
+        transfer();
+        setNode(st, CLK0, 0);
+        recalcNodeList(st);
+        uint8_t fa = readA(st), fx = readX(st), fy = readY(st), fs = readSP(st), fp = readP(st);
+        destroyChip(st);
+
+        uint16_t seen[64]; int ns = 0;
+        for (int i = 0; i < n; i++) {
+            int dup = 0;
+            for (int j = 0; j < ns; j++) if (seen[j] == addr[i]) dup = 1;
+            if (!dup) seen[ns++] = addr[i];
+        }
+        printf("{\"name\":\"%02x %02x %02x\",", image[pc], image[(uint16_t)(pc + 1)], image[(uint16_t)(pc + 2)]);
+        printf("\"initial\":{\"pc\":%u,\"s\":%u,\"a\":%u,\"x\":%u,\"y\":%u,\"p\":%u,\"ram\":[", pc, s, a, x, y, p);
+        for (int j = 0; j < ns; j++) printf("%s[%u,%u]", j ? "," : "", seen[j], image[seen[j]]);
+        printf("]},\"final\":{\"pc\":%u,\"s\":%u,\"a\":%u,\"x\":%u,\"y\":%u,\"p\":%u,\"ram\":[", next, fs, fa, fx, fy, (fp & 0xCF) | 0x20);
+        for (int j = 0; j < ns; j++) printf("%s[%u,%u]", j ? "," : "", seen[j], final_mem[seen[j]]);
+        printf("]},\"cycles\":[");
+        for (int i = 0; i < n; i++) printf("%s[%u,%u,\"%s\"]", i ? "," : "", addr[i], data[i], wr[i] ? "write" : "read");
+        printf("]}%s\n", t + 1 < count ? "," : "");
+    }
+    printf("]\n");
+    return 0;
+}
@@ -0,0 +1,44 @@
+// Runs the nes-headless binary the way CI does, without a window or audio device.
+use std::process::{Command, Output};
+
+fn headless(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_nes-headless"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn runs_frames_and_prints_the_frame_hash() {
+    let first = headless(&["--frames", "30", "--hash", "mario.nes"]);
+    assert!(first.status.success(), "{}", String::from_utf8_lossy(&first.stderr));
+    let stdout = String::from_utf8_lossy(&first.stdout);
+    assert!(stdout.contains("Ran 30 frames"), "{}", stdout);
+    assert!(stdout.contains("Frame hash "), "{}", stdout);
+
+    // the same run gives the same picture
+    let second = headless(&["--frames", "30", "--hash", "mario.nes"]);
+    assert_eq!(first.stdout, second.stdout);
+}
+
+#[test]
+fn stops_with_status_2_when_the_condition_is_never_met() {
+    let dir = std::env::temp_dir().join(format!("nes-rs-headless-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let dump = dir.join("ram.bin");
+
+    let output = headless(&[
+        "--frames",
+        "5",
+        "--until",
+        "0000=FF",
+        "--dump-ram",
+        dump.to_str().unwrap(),
+        "mario.nes",
+    ]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Ran 5 frames"));
+    assert_eq!(std::fs::read(&dump).unwrap().len(), 0x800);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
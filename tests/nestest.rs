@@ -0,0 +1,40 @@
+use nes_rs::apu::{sounds::SoundManager, NesAPU};
+use nes_rs::bus::Bus;
+use nes_rs::cartridge::Rom;
+use nes_rs::cpu::CPU;
+use nes_rs::joypad::Joypad;
+use nes_rs::ppu::NesPPU;
+use nes_rs::trace::trace;
+
+const CONTEXT_LINES: usize = 5;
+
+#[test]
+fn nestest_matches_log() {
+    let rom = Rom::new(&std::fs::read("nestest.nes").unwrap()).unwrap();
+    let log = std::fs::read_to_string("nestest.log").unwrap();
+    // every line, PPU and CYC columns included, has to match
+    let expected: Vec<&str> = log.lines().map(str::trim_end).collect();
+
+    let (_sound, txs) = SoundManager::new(44100.0);
+    let bus = Bus::new(rom, |_: &NesPPU, _: &mut Joypad| {}, NesAPU::new(txs));
+    let mut cpu = CPU::new(bus);
+    cpu.power_on();
+    // automation mode starts at $C000 instead of the reset vector
+    cpu.program_counter = 0xC000;
+
+    let mut ours: Vec<String> = Vec::new();
+    for (i, line) in expected.iter().enumerate() {
+        ours.push(trace(&mut cpu));
+        if ours[i] != *line {
+            let start = i.saturating_sub(CONTEXT_LINES);
+            let mut report = format!("nestest diverges at line {}\n", i + 1);
+            for (n, ok) in ours.iter().enumerate().take(i).skip(start) {
+                report += &format!("  {:5} {}\n", n + 1, ok);
+            }
+            report += &format!("- {:5} {}\n", i + 1, line);
+            report += &format!("+ {:5} {}\n", i + 1, ours[i]);
+            panic!("{}", report);
+        }
+        cpu.step();
+    }
+}
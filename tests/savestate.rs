@@ -0,0 +1,51 @@
+use nes_rs::cartridge::Rom;
+use nes_rs::emulator::Emulator;
+use nes_rs::joypad::JoypadButton;
+use nes_rs::savestate;
+
+const ROM_HASH: u64 = 0;
+
+fn mario() -> Emulator {
+    let rom = Rom::new(&std::fs::read("mario.nes").unwrap()).unwrap();
+    let mut emulator = Emulator::new(rom, 44100.0);
+    run_frames(&mut emulator, 40);
+    emulator.set_buttons(JoypadButton::START);
+    emulator
+}
+
+fn run_frames(emulator: &mut Emulator, frames: usize) {
+    for _ in 0..frames {
+        emulator.run_frame();
+        emulator.audio_samples();
+    }
+}
+
+#[test]
+fn save_run_load_restores_state() {
+    let mut emulator = mario();
+    let saved = savestate::save(emulator.cpu(), ROM_HASH);
+    run_frames(&mut emulator, 20);
+    let later = savestate::save(emulator.cpu(), ROM_HASH);
+    let later_frame = emulator.framebuffer().to_vec();
+    assert!(later != saved);
+
+    savestate::load(emulator.cpu_mut(), &saved, ROM_HASH).unwrap();
+    assert!(savestate::save(emulator.cpu(), ROM_HASH) == saved, "loaded state differs from the saved one");
+
+    // and the machine carries on exactly as it did the first time
+    run_frames(&mut emulator, 20);
+    assert!(savestate::save(emulator.cpu(), ROM_HASH) == later, "state diverged after loading");
+    assert!(emulator.framebuffer() == &later_frame[..], "picture diverged after loading");
+}
+
+#[test]
+fn damaged_state_leaves_machine_untouched() {
+    let mut emulator = mario();
+    let saved = savestate::save(emulator.cpu(), ROM_HASH);
+    run_frames(&mut emulator, 10);
+    let current = savestate::save(emulator.cpu(), ROM_HASH);
+
+    let truncated = &saved[..saved.len() - 100];
+    assert!(savestate::load(emulator.cpu_mut(), truncated, ROM_HASH).is_err());
+    assert!(savestate::save(emulator.cpu(), ROM_HASH) == current, "failed load changed the machine");
+}
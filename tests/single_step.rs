@@ -0,0 +1,287 @@
+// Per-opcode CPU conformance in the format of the SingleStepTests 6502 vectors
+// (https://github.com/SingleStepTests/65x02, nes6502/v1). Each opcode has a
+// `<opcode>.json` file, e.g. `a9.json`, holding single-instruction tests with
+// initial state, final state and per-cycle bus activity.
+//
+// The vectors are read from tests/fixtures/nes6502, or from the directory in
+// NES6502_TESTS, e.g. a checkout of the full SingleStepTests set. The bundled
+// fixtures are described in tests/fixtures/nes6502/README.md. Every opcode that
+// isn't skipped below has to have a file.
+use std::collections::BTreeMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+
+use nes_rs::cpu::{CpuBus, CpuFlags, Mem, CPU};
+use serde_json::Value;
+
+// Opcodes whose result depends on analog effects the vectors pin to one
+// particular chip (XAA, LXA, SHA, TAS, SHY, SHX).
+const UNSTABLE: [u8; 7] = [0x8B, 0xAB, 0x93, 0x9F, 0x9B, 0x9C, 0x9E];
+// JAM locks up the CPU rather than finishing an instruction.
+const JAM: [u8; 12] = [0x02, 0x12, 0x22, 0x32, 0x42, 0x52, 0x62, 0x72, 0x92, 0xB2, 0xD2, 0xF2];
+
+// B and bit 5 only exist in copies of P pushed to the stack, which the RAM check covers.
+const FLAG_MASK: u8 = !(CpuFlags::BREAK.bits() | CpuFlags::BREAK2.bits());
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Access {
+    Read,
+    Write,
+}
+
+// 64K of RAM with nothing mapped, recording every access in order.
+struct RecordingBus {
+    ram: Vec<u8>,
+    cycles: usize,
+    activity: Vec<(u16, u8, Access)>,
+}
+
+impl RecordingBus {
+    fn new() -> Self {
+        RecordingBus {
+            ram: vec![0; 0x10000],
+            cycles: 0,
+            activity: Vec::new(),
+        }
+    }
+}
+
+impl Mem for RecordingBus {
+    fn mem_read(&mut self, addr: u16) -> u8 {
+        let data = self.ram[addr as usize];
+        self.activity.push((addr, data, Access::Read));
+        data
+    }
+
+    fn mem_write(&mut self, addr: u16, data: u8) {
+        self.ram[addr as usize] = data;
+        self.activity.push((addr, data, Access::Write));
+    }
+}
+
+impl CpuBus for RecordingBus {
+    fn tick(&mut self, cycles: u8) {
+        self.cycles += cycles as usize;
+    }
+
+    fn poll_nmi_status(&mut self) -> Option<u8> {
+        None
+    }
+
+    fn poll_irq_status(&self) -> bool {
+        false
+    }
+}
+
+struct State {
+    pc: u16,
+    s: u8,
+    a: u8,
+    x: u8,
+    y: u8,
+    p: u8,
+    ram: Vec<(u16, u8)>,
+}
+
+impl State {
+    fn from_json(value: &Value) -> Self {
+        let byte = |key: &str| value[key].as_u64().unwrap() as u8;
+        State {
+            pc: value["pc"].as_u64().unwrap() as u16,
+            s: byte("s"),
+            a: byte("a"),
+            x: byte("x"),
+            y: byte("y"),
+            p: byte("p"),
+            ram: value["ram"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|cell| (cell[0].as_u64().unwrap() as u16, cell[1].as_u64().unwrap() as u8))
+                .collect(),
+        }
+    }
+}
+
+struct Vector {
+    name: String,
+    initial: State,
+    expected: State,
+    cycles: Vec<(u16, u8, Access)>,
+}
+
+impl Vector {
+    fn from_json(value: &Value) -> Self {
+        Vector {
+            name: value["name"].as_str().unwrap().to_string(),
+            initial: State::from_json(&value["initial"]),
+            expected: State::from_json(&value["final"]),
+            cycles: value["cycles"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|cycle| {
+                    let access = match cycle[2].as_str().unwrap() {
+                        "read" => Access::Read,
+                        _ => Access::Write,
+                    };
+                    (cycle[0].as_u64().unwrap() as u16, cycle[1].as_u64().unwrap() as u8, access)
+                })
+                .collect(),
+        }
+    }
+}
+
+struct Outcome {
+    mismatches: Vec<String>,
+    cycles: usize,
+    activity: Vec<(u16, u8, Access)>,
+}
+
+fn run(vector: &Vector) -> Outcome {
+    let mut bus = RecordingBus::new();
+    for (addr, data) in &vector.initial.ram {
+        bus.ram[*addr as usize] = *data;
+    }
+    let mut cpu = CPU::new(bus);
+    cpu.program_counter = vector.initial.pc;
+    cpu.stack_pointer = vector.initial.s;
+    cpu.reg_a = vector.initial.a;
+    cpu.reg_x = vector.initial.x;
+    cpu.reg_y = vector.initial.y;
+    cpu.status = CpuFlags::from_bits_truncate(vector.initial.p);
+
+    let result = panic::catch_unwind(AssertUnwindSafe(|| cpu.step()));
+    if let Err(err) = result {
+        let message = err
+            .downcast_ref::<String>()
+            .cloned()
+            .or_else(|| err.downcast_ref::<&str>().map(|s| s.to_string()))
+            .unwrap_or_default();
+        return Outcome {
+            mismatches: vec![format!("panicked: {}", message)],
+            cycles: cpu.bus.cycles,
+            activity: cpu.bus.activity,
+        };
+    }
+
+    let expected = &vector.expected;
+    let mut mismatches = Vec::new();
+    let mut check = |name: &str, ours: u16, theirs: u16| {
+        if ours != theirs {
+            mismatches.push(format!("{} is {:02X}, expected {:02X}", name, ours, theirs));
+        }
+    };
+    check("PC", cpu.program_counter, expected.pc);
+    check("S", cpu.stack_pointer as u16, expected.s as u16);
+    check("A", cpu.reg_a as u16, expected.a as u16);
+    check("X", cpu.reg_x as u16, expected.x as u16);
+    check("Y", cpu.reg_y as u16, expected.y as u16);
+    check("P", (cpu.status.bits() & FLAG_MASK) as u16, (expected.p & FLAG_MASK) as u16);
+    for (addr, data) in &expected.ram {
+        let ours = cpu.bus.ram[*addr as usize];
+        if ours != *data {
+            mismatches.push(format!("${:04X} is {:02X}, expected {:02X}", addr, ours, data));
+        }
+    }
+
+    Outcome {
+        mismatches,
+        cycles: cpu.bus.cycles,
+        activity: cpu.bus.activity,
+    }
+}
+
+fn fixtures_dir() -> PathBuf {
+    match std::env::var_os("NES6502_TESTS") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from("tests/fixtures/nes6502"),
+    }
+}
+
+fn load_vectors(opcode: u8) -> Option<Vec<Vector>> {
+    let path = fixtures_dir().join(format!("{:02x}.json", opcode));
+    let text = std::fs::read_to_string(path).ok()?;
+    let json: Value = serde_json::from_str(&text).unwrap();
+    Some(json.as_array().unwrap().iter().map(Vector::from_json).collect())
+}
+
+// Runs every available opcode through `check` and fails with one line per opcode
+// that had mismatching vectors, showing the first of them.
+fn conformance<F>(skip: &[u8], check: F)
+where
+    F: Fn(&Vector, &Outcome) -> Option<String>,
+{
+    let mut failures = BTreeMap::new();
+    let mut tested = 0;
+    // panics are reported as mismatches, keep them from flooding the output
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+    for opcode in 0..=255u8 {
+        if skip.contains(&opcode) {
+            continue;
+        }
+        let vectors = match load_vectors(opcode) {
+            Some(vectors) => vectors,
+            None => {
+                failures.insert(opcode, "no vectors".to_string());
+                continue;
+            }
+        };
+        tested += 1;
+
+        let mut failed = 0;
+        let mut first = None;
+        for vector in &vectors {
+            if let Some(report) = check(vector, &run(vector)) {
+                failed += 1;
+                first.get_or_insert_with(|| format!("[{}] {}", vector.name, report));
+            }
+        }
+        if let Some(first) = first {
+            failures.insert(opcode, format!("{}/{} failed, first: {}", failed, vectors.len(), first));
+        }
+    }
+    panic::set_hook(hook);
+
+    if !failures.is_empty() {
+        let report: Vec<String> = failures
+            .iter()
+            .map(|(opcode, failure)| format!("{:02X}: {}", opcode, failure))
+            .collect();
+        panic!(
+            "{} of {} opcodes failed ({} tested from {})\n{}",
+            failures.len(),
+            256 - skip.len(),
+            tested,
+            fixtures_dir().display(),
+            report.join("\n")
+        );
+    }
+}
+
+#[test]
+fn registers_and_memory() {
+    let skip: Vec<u8> = UNSTABLE.iter().chain(JAM.iter()).copied().collect();
+    conformance(&skip, |_, outcome| {
+        (!outcome.mismatches.is_empty()).then(|| outcome.mismatches.join(", "))
+    });
+}
+
+// Every access is one cycle, so the accesses have to match the vectors one for one,
+// dummy reads and writes included.
+#[test]
+fn bus_activity() {
+    let skip: Vec<u8> = UNSTABLE.iter().chain(JAM.iter()).copied().collect();
+    conformance(&skip, |vector, outcome| {
+        (outcome.activity != vector.cycles || outcome.cycles != vector.cycles.len()).then(|| {
+            format!(
+                "took {} cycles, expected {}; accesses {:?}, expected {:?}",
+                outcome.cycles,
+                vector.cycles.len(),
+                outcome.activity,
+                vector.cycles
+            )
+        })
+    });
+}